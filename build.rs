@@ -1,7 +1,23 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The migrations are embedded by `sqlx::migrate!`.
+    println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-env-changed=PROTOC");
+
+    // Prefer the `protoc` provided by the environment (e.g. Devenv or Nix),
+    // and fall back to the vendored binary so a plain `cargo build` works.
+    if std::env::var_os("PROTOC").is_none() && !protoc_in_path() {
+        // SAFETY: the build script is single-threaded.
+        unsafe { std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?) };
+    }
+
     tonic_build::configure()
         .build_client(true)
-        .build_server(false)
-        .compile(&["proto/dbrunner.proto"], &["proto"])?;
+        .build_server(std::env::var_os("CARGO_FEATURE_DBRUNNER_SERVER").is_some())
+        .compile_protos(&["proto/dbrunner.proto"], &["proto"])?;
     Ok(())
 }
+
+fn protoc_in_path() -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join("protoc").is_file()))
+}
@@ -0,0 +1,4 @@
+-- Add migration script here
+
+-- error is the error message returned by dbrunner if the attempt fails to run.
+ALTER TABLE dp_attempt_events ADD COLUMN error TEXT;
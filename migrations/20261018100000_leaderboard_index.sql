@@ -0,0 +1,9 @@
+-- Add migration script here
+
+-- Look up the members of a group when ranking them on the leaderboard.
+CREATE INDEX dp_users_group_id_idx ON dp_users (group_id);
+
+-- Scan the attempts of a user within a time window.
+CREATE INDEX dp_attempt_events_user_id_created_at_idx ON dp_attempt_events (
+    user_id, created_at
+);
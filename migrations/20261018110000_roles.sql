@@ -0,0 +1,10 @@
+-- Add migration script here
+
+CREATE TYPE dp_user_role AS ENUM ('student', 'teacher', 'admin');
+
+ALTER TABLE dp_users ADD COLUMN role DP_USER_ROLE NOT NULL DEFAULT 'student';
+
+-- owner_id is the teacher who manages the group.
+ALTER TABLE dp_groups ADD COLUMN owner_id VARCHAR(255) REFERENCES dp_users ON DELETE SET NULL;
+
+CREATE INDEX dp_groups_owner_id_idx ON dp_groups (owner_id);
@@ -0,0 +1,19 @@
+-- Add migration script here
+
+-- The personal access tokens for the scripts. Only the SHA-256 hash of the
+-- token is stored, and the plaintext is shown once on creation.
+CREATE TABLE dp_api_tokens (
+    api_token_id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id VARCHAR(255) NOT NULL REFERENCES dp_users ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+
+    token_hash BYTEA NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+
+    expires_at TIMESTAMP WITH TIME ZONE,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX dp_api_tokens_user_id_idx ON dp_api_tokens (user_id);
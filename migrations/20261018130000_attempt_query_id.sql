@@ -0,0 +1,7 @@
+-- Add migration script here
+
+-- query_id is the ID of the result kept by dbrunner if the attempt runs, so
+-- that the result can only be retrieved by the user who ran it.
+ALTER TABLE dp_attempt_events ADD COLUMN query_id TEXT;
+
+CREATE INDEX dp_attempt_events_query_id_idx ON dp_attempt_events (query_id);
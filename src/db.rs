@@ -1,7 +1,7 @@
 //! Operate with the database and the models.
 
 pub mod cursor;
-pub use cursor::Cursor;
+pub use cursor::{KeysetCursor, Page};
 pub mod user;
 pub use user::*;
 pub mod event;
@@ -10,6 +10,10 @@ pub mod question;
 pub use question::*;
 pub mod schema;
 pub use schema::*;
+pub mod progress;
+pub use progress::*;
+pub mod api_token;
+pub use api_token::*;
 
 pub type Pool = sqlx::Pool<sqlx::Postgres>;
 
@@ -41,6 +45,18 @@ pub enum Error {
         id: ecow::EcoString,
     },
 
+    #[error("{entity} already exists: {id}")]
+    AlreadyExists {
+        entity: &'static str,
+        id: ecow::EcoString,
+    },
+
+    #[error("{entity} is still in use: {id}")]
+    InUse {
+        entity: &'static str,
+        id: ecow::EcoString,
+    },
+
     #[error("id must be a positive integer")]
     NotPositiveID,
 
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use ecow::eco_format;
+
+use super::{Error, Executor};
+
+/// A personal access token, without the hash of the token.
+#[derive(Debug, Clone)]
+pub struct ApiToken {
+    pub api_token_id: i64,
+    pub user_id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct ApiTokenCreateParameter<'a> {
+    pub user_id: &'a str,
+    pub name: &'a str,
+    /// The SHA-256 hash of the token.
+    pub token_hash: &'a [u8],
+    pub scopes: &'a [String],
+    /// The token never expires if `None`.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(skip(conn, token_hash))]
+pub async fn create_api_token(
+    conn: impl Executor<'_>,
+    ApiTokenCreateParameter {
+        user_id,
+        name,
+        token_hash,
+        scopes,
+        expires_at,
+    }: ApiTokenCreateParameter<'_>,
+) -> Result<ApiToken, Error> {
+    tracing::debug!("Creating API token");
+
+    let token = sqlx::query_as!(
+        ApiToken,
+        r#"
+        INSERT INTO dp_api_tokens (user_id, name, token_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING api_token_id, user_id, name, scopes, expires_at, last_used_at, created_at
+        "#,
+        user_id,
+        name,
+        token_hash,
+        scopes,
+        expires_at,
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(token)
+}
+
+/// List the tokens of the user which have not been revoked, from the newest
+/// to the oldest. The expired tokens are included.
+#[tracing::instrument(skip(conn))]
+pub async fn list_api_tokens(
+    conn: impl Executor<'_>,
+    user_id: &str,
+) -> Result<Vec<ApiToken>, Error> {
+    tracing::debug!("Listing API tokens from database");
+
+    let tokens = sqlx::query_as!(
+        ApiToken,
+        r#"
+        SELECT api_token_id, user_id, name, scopes, expires_at, last_used_at, created_at
+        FROM dp_api_tokens
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY api_token_id DESC
+        "#,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(tokens)
+}
+
+/// Revoke the token of the user.
+#[tracing::instrument(skip(conn))]
+pub async fn revoke_api_token(
+    conn: impl Executor<'_>,
+    user_id: &str,
+    api_token_id: i64,
+) -> Result<(), Error> {
+    tracing::debug!("Revoking API token");
+
+    let affected_rows = sqlx::query!(
+        r#"
+        UPDATE dp_api_tokens
+        SET revoked_at = now()
+        WHERE api_token_id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        api_token_id,
+        user_id,
+    )
+    .execute(conn)
+    .await?
+    .rows_affected();
+
+    if affected_rows == 0 {
+        return Err(Error::NotFound {
+            entity: "api_token",
+            id: eco_format!("{api_token_id}"),
+        });
+    }
+
+    Ok(())
+}
+
+/// Look up the token by its hash and record the use of it.
+///
+/// Returns `None` if the token does not exist, has been revoked or expired,
+/// or its user has been deleted.
+#[tracing::instrument(skip(conn, token_hash))]
+pub async fn use_api_token(
+    conn: impl Executor<'_>,
+    token_hash: &[u8],
+) -> Result<Option<ApiToken>, Error> {
+    tracing::debug!("Using API token");
+
+    let token = sqlx::query_as!(
+        ApiToken,
+        r#"
+        UPDATE dp_api_tokens t
+        SET last_used_at = now()
+        FROM dp_users u
+        WHERE t.token_hash = $1
+          AND t.revoked_at IS NULL
+          AND (t.expires_at IS NULL OR t.expires_at > now())
+          AND u.user_id = t.user_id
+          AND u.deleted_at IS NULL
+        RETURNING t.api_token_id, t.user_id, t.name, t.scopes,
+                  t.expires_at, t.last_used_at, t.created_at
+        "#,
+        token_hash,
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(token)
+}
@@ -1,52 +1,113 @@
+/// The cursor for keyset pagination over an ordered key.
+///
+/// Rows strictly between `after` and `before` are selected. `first` takes
+/// the rows from the start of that range, and `last` takes them from the end.
 #[derive(Debug, Clone, Copy, Default)]
-pub struct Cursor {
-    pub offset: Option<i64>,
-    pub limit: Option<i64>,
+pub struct KeysetCursor<K> {
+    pub after: Option<K>,
+    pub before: Option<K>,
+    pub first: Option<i64>,
+    pub last: Option<i64>,
 }
 
-impl Cursor {
-    /// Get the offset value, defaulting to 0.
-    pub fn get_offset(&self) -> i64 {
-        self.offset.unwrap_or(0)
+impl<K> KeysetCursor<K> {
+    /// Whether the rows should be taken from the end of the range.
+    pub fn is_backward(&self) -> bool {
+        self.first.is_none() && self.last.is_some()
     }
 
-    /// Get the limit value, defaulting to 10 and capped at 100.
+    /// Get the page size, defaulting to 10 and capped at 100.
     pub fn get_limit(&self) -> i64 {
-        self.limit.map(|n| n.min(100)).unwrap_or(10)
+        self.first
+            .or(self.last)
+            .map(|n| n.clamp(0, 100))
+            .unwrap_or(10)
+    }
+}
+
+/// A page of rows fetched with a [`KeysetCursor`].
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub has_previous_page: bool,
+    pub has_next_page: bool,
+}
+
+impl<T> Page<T> {
+    /// Build a page from the rows fetched with `cursor`.
+    ///
+    /// `rows` should be fetched in the direction of the cursor with one extra
+    /// row (`cursor.get_limit() + 1`), which is used to tell whether there are
+    /// more rows in that direction.
+    pub fn from_rows<K>(mut rows: Vec<T>, cursor: &KeysetCursor<K>) -> Self {
+        let limit = cursor.get_limit() as usize;
+        let has_more = rows.len() > limit;
+        rows.truncate(limit);
+
+        if cursor.is_backward() {
+            rows.reverse();
+
+            Self {
+                items: rows,
+                has_previous_page: has_more,
+                has_next_page: cursor.before.is_some(),
+            }
+        } else {
+            Self {
+                items: rows,
+                has_previous_page: cursor.after.is_some(),
+                has_next_page: has_more,
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     #[test]
-    fn test_cursor_default() {
-        let cursor = super::Cursor {
-            offset: None,
-            limit: None,
-        };
+    fn test_keyset_cursor_default() {
+        let cursor = super::KeysetCursor::<i64>::default();
 
-        assert_eq!(cursor.get_offset(), 0);
+        assert!(!cursor.is_backward());
         assert_eq!(cursor.get_limit(), 10);
     }
 
     #[test]
-    fn test_cursor_acceptable() {
-        let cursor = super::Cursor {
-            offset: Some(99),
-            limit: Some(99),
+    fn test_keyset_cursor_backward() {
+        let cursor = super::KeysetCursor::<i64> {
+            before: Some(10),
+            last: Some(114514),
+            ..Default::default()
         };
 
-        assert_eq!(cursor.get_offset(), 99);
-        assert_eq!(cursor.get_limit(), 99);
+        assert!(cursor.is_backward());
+        assert_eq!(cursor.get_limit(), 100);
     }
 
     #[test]
-    fn test_cursor_rounded() {
-        let cursor = super::Cursor {
-            offset: None,
-            limit: Some(114514),
+    fn test_page_forward() {
+        let cursor = super::KeysetCursor::<i64> {
+            after: Some(1),
+            first: Some(2),
+            ..Default::default()
         };
+        let page = super::Page::from_rows(vec![2, 3, 4], &cursor);
 
-        assert_eq!(cursor.get_limit(), 100);
+        assert_eq!(page.items, vec![2, 3]);
+        assert!(page.has_previous_page);
+        assert!(page.has_next_page);
+    }
+
+    #[test]
+    fn test_page_backward() {
+        let cursor = super::KeysetCursor::<i64> {
+            last: Some(3),
+            ..Default::default()
+        };
+        let page = super::Page::from_rows(vec![3, 2, 1], &cursor);
+
+        assert_eq!(page.items, vec![1, 2, 3]);
+        assert!(!page.has_previous_page);
+        assert!(!page.has_next_page);
     }
 }
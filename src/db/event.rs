@@ -1,4 +1,9 @@
-use super::{Error, Executor};
+use chrono::{DateTime, Utc};
+
+use super::{
+    Error, Executor,
+    cursor::{KeysetCursor, Page},
+};
 
 #[derive(Debug, Clone, Copy, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "dp_attempt_status", rename_all = "lowercase")]
@@ -58,6 +63,80 @@ pub async fn mark_attempt_event(
     Ok(())
 }
 
+/// Mark the attempt event as failed with the error message from dbrunner.
+#[tracing::instrument(skip(conn))]
+pub async fn mark_attempt_event_failed(
+    conn: impl Executor<'_>,
+    event_id: i64,
+    error: &str,
+) -> Result<(), Error> {
+    tracing::debug!("Marking attempt event as failed in database");
+
+    sqlx::query!(
+        r#"
+        UPDATE dp_attempt_events
+        SET status = $1, error = $2
+        WHERE attempt_event_id = $3
+        "#,
+        AttemptStatus::Failed as AttemptStatus,
+        error,
+        event_id,
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Record the ID of the query result kept by dbrunner for the attempt event.
+#[tracing::instrument(skip(conn))]
+pub async fn set_attempt_event_query_id(
+    conn: impl Executor<'_>,
+    event_id: i64,
+    query_id: &str,
+) -> Result<(), Error> {
+    tracing::debug!("Recording query ID of attempt event in database");
+
+    sqlx::query!(
+        r#"
+        UPDATE dp_attempt_events
+        SET query_id = $1
+        WHERE attempt_event_id = $2
+        "#,
+        query_id,
+        event_id,
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Check if the query result kept by dbrunner is produced by an attempt of
+/// the user.
+#[tracing::instrument(skip(conn))]
+pub async fn is_query_owned_by(
+    conn: impl Executor<'_>,
+    user_id: &str,
+    query_id: &str,
+) -> Result<bool, Error> {
+    tracing::debug!("Checking owner of query in database");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM dp_attempt_events
+            WHERE query_id = $1 AND user_id = $2
+        ) AS "owned!"
+        "#,
+        query_id,
+        user_id,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
 #[tracing::instrument(skip(conn))]
 pub async fn create_solution_event(
     conn: impl Executor<'_>,
@@ -80,3 +159,232 @@ pub async fn create_solution_event(
 
     Ok(event.solution_event_id)
 }
+
+#[derive(Debug, Clone)]
+pub struct SolutionEvent {
+    pub solution_event_id: i64,
+    pub user_id: String,
+    pub question_id: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// List the solution events of a user, from the newest to the oldest, with
+/// keyset pagination on the event ID.
+#[tracing::instrument(skip(conn))]
+pub async fn list_solution_events(
+    conn: impl Executor<'_>,
+    user_id: &str,
+    cursor: KeysetCursor<i64>,
+) -> Result<Page<SolutionEvent>, Error> {
+    tracing::debug!("Listing solution events from database");
+
+    let rows = if cursor.is_backward() {
+        sqlx::query_as!(
+            SolutionEvent,
+            r#"
+            SELECT solution_event_id, user_id, question_id, created_at
+            FROM dp_solution_events
+            WHERE user_id = $1
+                AND ($2::bigint IS NULL OR solution_event_id < $2)
+                AND ($3::bigint IS NULL OR solution_event_id > $3)
+            ORDER BY solution_event_id
+            LIMIT $4
+            "#,
+            user_id,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    } else {
+        sqlx::query_as!(
+            SolutionEvent,
+            r#"
+            SELECT solution_event_id, user_id, question_id, created_at
+            FROM dp_solution_events
+            WHERE user_id = $1
+                AND ($2::bigint IS NULL OR solution_event_id < $2)
+                AND ($3::bigint IS NULL OR solution_event_id > $3)
+            ORDER BY solution_event_id DESC
+            LIMIT $4
+            "#,
+            user_id,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    };
+
+    Ok(Page::from_rows(rows, &cursor))
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn count_solution_events(conn: impl Executor<'_>, user_id: &str) -> Result<i64, Error> {
+    tracing::debug!("Counting solution events in database");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM dp_solution_events
+        WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+/// List all the solution events of a user, from the oldest to the newest.
+#[tracing::instrument(skip(conn))]
+pub async fn list_all_solution_events(
+    conn: impl Executor<'_>,
+    user_id: &str,
+) -> Result<Vec<SolutionEvent>, Error> {
+    tracing::debug!("Listing all solution events from database");
+
+    sqlx::query_as!(
+        SolutionEvent,
+        r#"
+        SELECT solution_event_id, user_id, question_id, created_at
+        FROM dp_solution_events
+        WHERE user_id = $1
+        ORDER BY created_at, solution_event_id
+        "#,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+#[derive(Debug, Clone)]
+pub struct AttemptEvent {
+    pub attempt_event_id: i64,
+    pub user_id: String,
+    pub question_id: i64,
+    pub query: String,
+    pub status: AttemptStatus,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The conditions to filter the attempt events of a user with.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AttemptFilter {
+    pub question_id: Option<i64>,
+    pub status: Option<AttemptStatus>,
+}
+
+/// List the attempt events of a user, from the newest to the oldest, with
+/// keyset pagination on the event ID.
+#[tracing::instrument(skip(conn))]
+pub async fn list_attempt_events(
+    conn: impl Executor<'_>,
+    user_id: &str,
+    filter: AttemptFilter,
+    cursor: KeysetCursor<i64>,
+) -> Result<Page<AttemptEvent>, Error> {
+    tracing::debug!("Listing attempt events from database");
+
+    let rows = if cursor.is_backward() {
+        sqlx::query_as!(
+            AttemptEvent,
+            r#"
+            SELECT attempt_event_id, user_id, question_id, query, status AS "status: _", error, created_at
+            FROM dp_attempt_events
+            WHERE user_id = $1
+                AND ($2::bigint IS NULL OR question_id = $2)
+                AND ($3::dp_attempt_status IS NULL OR status = $3)
+                AND ($4::bigint IS NULL OR attempt_event_id < $4)
+                AND ($5::bigint IS NULL OR attempt_event_id > $5)
+            ORDER BY attempt_event_id
+            LIMIT $6
+            "#,
+            user_id,
+            filter.question_id,
+            filter.status as Option<AttemptStatus>,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    } else {
+        sqlx::query_as!(
+            AttemptEvent,
+            r#"
+            SELECT attempt_event_id, user_id, question_id, query, status AS "status: _", error, created_at
+            FROM dp_attempt_events
+            WHERE user_id = $1
+                AND ($2::bigint IS NULL OR question_id = $2)
+                AND ($3::dp_attempt_status IS NULL OR status = $3)
+                AND ($4::bigint IS NULL OR attempt_event_id < $4)
+                AND ($5::bigint IS NULL OR attempt_event_id > $5)
+            ORDER BY attempt_event_id DESC
+            LIMIT $6
+            "#,
+            user_id,
+            filter.question_id,
+            filter.status as Option<AttemptStatus>,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    };
+
+    Ok(Page::from_rows(rows, &cursor))
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn count_attempt_events(
+    conn: impl Executor<'_>,
+    user_id: &str,
+    filter: AttemptFilter,
+) -> Result<i64, Error> {
+    tracing::debug!("Counting attempt events in database");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM dp_attempt_events
+        WHERE user_id = $1
+            AND ($2::bigint IS NULL OR question_id = $2)
+            AND ($3::dp_attempt_status IS NULL OR status = $3)
+        "#,
+        user_id,
+        filter.question_id,
+        filter.status as Option<AttemptStatus>,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+/// List all the attempt events of a user, from the oldest to the newest.
+#[tracing::instrument(skip(conn))]
+pub async fn list_all_attempt_events(
+    conn: impl Executor<'_>,
+    user_id: &str,
+) -> Result<Vec<AttemptEvent>, Error> {
+    tracing::debug!("Listing all attempt events from database");
+
+    sqlx::query_as!(
+        AttemptEvent,
+        r#"
+        SELECT attempt_event_id, user_id, question_id, query, status AS "status: _", error, created_at
+        FROM dp_attempt_events
+        WHERE user_id = $1
+        ORDER BY attempt_event_id
+        "#,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
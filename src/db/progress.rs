@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+
+use super::{Difficulty, Error, Executor};
+
+/// The progress of a user on the questions with the same difficulty and type.
+///
+/// Every question falls into exactly one of `solved`, `attempted` and
+/// `untouched`: a question is only counted as attempted if none of its
+/// attempts has passed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressBreakdown {
+    pub difficulty: Difficulty,
+    pub question_type: String,
+    pub solved: i64,
+    pub attempted: i64,
+    pub untouched: i64,
+}
+
+/// The first passed attempt of a user on a question.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirstSolve {
+    pub question_id: i64,
+    pub solved_at: DateTime<Utc>,
+}
+
+/// Count the solved, attempted and untouched questions of a user, grouped by
+/// the difficulty and the type of the questions.
+#[tracing::instrument(skip(conn))]
+pub async fn get_progress_breakdown(
+    conn: impl Executor<'_>,
+    user_id: &str,
+) -> Result<Vec<ProgressBreakdown>, Error> {
+    tracing::debug!("Aggregating user progress from database");
+
+    sqlx::query_as!(
+        ProgressBreakdown,
+        r#"
+        WITH attempted AS (
+            SELECT question_id, bool_or(status = 'passed') AS solved
+            FROM dp_attempt_events
+            WHERE user_id = $1
+            GROUP BY question_id
+        )
+        SELECT
+            q.difficulty AS "difficulty: Difficulty",
+            q.type AS question_type,
+            COUNT(*) FILTER (WHERE a.solved) AS "solved!",
+            COUNT(*) FILTER (WHERE NOT a.solved) AS "attempted!",
+            COUNT(*) FILTER (WHERE a.question_id IS NULL) AS "untouched!"
+        FROM dp_questions q
+        LEFT JOIN attempted a ON a.question_id = q.question_id
+        WHERE q.deleted_at IS NULL
+        GROUP BY q.difficulty, q.type
+        ORDER BY q.difficulty, q.type
+        "#,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+/// List the time a user first passed each question, from the earliest to the
+/// latest.
+#[tracing::instrument(skip(conn))]
+pub async fn list_first_solves(
+    conn: impl Executor<'_>,
+    user_id: &str,
+) -> Result<Vec<FirstSolve>, Error> {
+    tracing::debug!("Listing first solves from database");
+
+    sqlx::query_as!(
+        FirstSolve,
+        r#"
+        SELECT e.question_id, MIN(e.created_at) AS "solved_at!"
+        FROM dp_attempt_events e
+        JOIN dp_questions q ON q.question_id = e.question_id
+        WHERE e.user_id = $1 AND e.status = 'passed' AND q.deleted_at IS NULL
+        GROUP BY e.question_id
+        ORDER BY MIN(e.created_at), e.question_id
+        "#,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+/// The ranking of a group member on the leaderboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    pub user_id: String,
+    /// The rank of the member, starting from 1. Tied members share a rank.
+    pub rank: i64,
+    /// The number of questions the member has passed.
+    pub passed: i64,
+    /// The passed questions weighted by difficulty: 1 for easy, 2 for medium
+    /// and 3 for hard questions.
+    pub score: i64,
+    /// The total seconds from the first attempt to the first pass of each
+    /// passed question, which breaks the ties of `score`.
+    pub time_to_pass: Option<i64>,
+}
+
+/// Rank the members of a group by the questions they have first passed since
+/// `since`, or of all time if `since` is `None`.
+#[tracing::instrument(skip(conn))]
+pub async fn get_group_leaderboard(
+    conn: impl Executor<'_>,
+    group_id: i64,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<LeaderboardEntry>, Error> {
+    tracing::debug!("Ranking group members from database");
+
+    sqlx::query_as!(
+        LeaderboardEntry,
+        r#"
+        WITH members AS (
+            SELECT user_id
+            FROM dp_users
+            WHERE group_id = $1 AND deleted_at IS NULL
+        ),
+        -- The first passes inside the window, without an earlier pass of the
+        -- same question, so that only the window is scanned.
+        passed AS (
+            SELECT e.user_id, e.question_id, MIN(e.created_at) AS first_passed_at
+            FROM members m
+            JOIN dp_attempt_events e ON e.user_id = m.user_id
+            WHERE e.status = 'passed'
+                AND e.created_at >= COALESCE($2::timestamptz, '-infinity')
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM dp_attempt_events p
+                    WHERE p.user_id = e.user_id AND p.question_id = e.question_id
+                        AND p.status = 'passed' AND p.created_at < COALESCE($2::timestamptz, '-infinity')
+                )
+            GROUP BY e.user_id, e.question_id
+        ),
+        scores AS (
+            SELECT
+                p.user_id,
+                COUNT(*) AS passed,
+                SUM(
+                    CASE q.difficulty WHEN 'easy' THEN 1 WHEN 'medium' THEN 2 ELSE 3 END
+                ) AS score,
+                SUM(p.first_passed_at - (
+                    SELECT MIN(a.created_at)
+                    FROM dp_attempt_events a
+                    WHERE a.user_id = p.user_id AND a.question_id = p.question_id
+                )) AS time_to_pass
+            FROM passed p
+            JOIN dp_questions q ON q.question_id = p.question_id
+            WHERE q.deleted_at IS NULL
+            GROUP BY p.user_id
+        )
+        SELECT
+            m.user_id,
+            RANK() OVER (
+                ORDER BY COALESCE(s.score, 0) DESC, s.time_to_pass ASC NULLS LAST
+            ) AS "rank!",
+            COALESCE(s.passed, 0) AS "passed!",
+            COALESCE(s.score, 0) AS "score!",
+            EXTRACT(EPOCH FROM s.time_to_pass)::bigint AS time_to_pass
+        FROM members m
+        LEFT JOIN scores s ON s.user_id = m.user_id
+        ORDER BY "rank!", m.user_id
+        "#,
+        group_id,
+        since,
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
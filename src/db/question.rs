@@ -2,9 +2,12 @@
 
 use chrono::{DateTime, Utc};
 
-use super::{cursor::Cursor, Error, Executor};
+use super::{
+    Error, Executor,
+    cursor::{KeysetCursor, Page},
+};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
 pub struct Question {
     pub question_id: i64,
     pub schema_id: Option<String>,
@@ -24,28 +27,151 @@ pub enum Difficulty {
     Hard,
 }
 
+/// The conditions to filter the questions with.
+#[derive(Debug, Clone, Default)]
+pub struct QuestionFilter {
+    pub difficulty: Option<Difficulty>,
+    pub question_type: Option<String>,
+    pub schema_id: Option<String>,
+    /// Match the questions whose title or description contains this text.
+    pub search: Option<String>,
+}
+
+/// The order of the listed questions.
+///
+/// Ties are always broken by the question ID.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuestionOrder {
+    #[default]
+    Id,
+    Newest,
+    Difficulty,
+    Title,
+}
+
+impl QuestionOrder {
+    /// The columns to sort by, ending with the question ID.
+    fn columns(&self) -> &'static str {
+        match self {
+            QuestionOrder::Id => "question_id",
+            QuestionOrder::Newest => "created_at, question_id",
+            QuestionOrder::Difficulty => "difficulty, question_id",
+            QuestionOrder::Title => "title, question_id",
+        }
+    }
+
+    fn is_descending(&self) -> bool {
+        matches!(self, QuestionOrder::Newest)
+    }
+}
+
+const QUESTION_COLUMNS: &str = "question_id, schema_id, type AS question_type, difficulty, title, description, created_at, updated_at";
+
+fn push_question_filter<'a>(
+    query: &mut sqlx::QueryBuilder<'a, sqlx::Postgres>,
+    filter: &'a QuestionFilter,
+) {
+    query.push(" WHERE deleted_at IS NULL");
+
+    if let Some(difficulty) = filter.difficulty {
+        query.push(" AND difficulty = ").push_bind(difficulty);
+    }
+    if let Some(question_type) = &filter.question_type {
+        query.push(" AND type = ").push_bind(question_type);
+    }
+    if let Some(schema_id) = &filter.schema_id {
+        query.push(" AND schema_id = ").push_bind(schema_id);
+    }
+    if let Some(search) = &filter.search {
+        let pattern = format!(
+            "%{}%",
+            search
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        query
+            .push(" AND (title ILIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR description ILIKE ")
+            .push_bind(pattern)
+            .push(")");
+    }
+}
+
+/// List the questions matching `filter` with keyset pagination.
+///
+/// The cursor is the ID of a question. The keyset is resolved from the sort
+/// columns of that question, so a cursor stays valid even if its question is
+/// soft-deleted later.
 #[tracing::instrument(skip(conn))]
 pub async fn list_questions(
     conn: impl Executor<'_>,
-    cursor: Cursor,
-) -> Result<Vec<Question>, Error> {
+    filter: &QuestionFilter,
+    order: QuestionOrder,
+    cursor: KeysetCursor<i64>,
+) -> Result<Page<Question>, Error> {
     tracing::debug!("Listing questions from database");
 
-    sqlx::query_as!(
-        Question,
-        r#"
-        SELECT question_id, schema_id, type AS question_type, difficulty AS "difficulty: _", title, description, created_at, updated_at
-        FROM dp_questions
-        WHERE deleted_at IS NULL
-        ORDER BY question_id
-        LIMIT $1 OFFSET $2;
-        "#,
-        cursor.get_limit(),
-        cursor.get_offset()
-    )
-    .fetch_all(conn)
-    .await
-    .map_err(Error::DatabaseError)
+    let columns = order.columns();
+    // Whether the rows are scanned in the descending order of `columns`.
+    let descending = order.is_descending() != cursor.is_backward();
+    let (after_op, before_op) = if order.is_descending() {
+        ("<", ">")
+    } else {
+        (">", "<")
+    };
+
+    let mut query = sqlx::QueryBuilder::new(format!("SELECT {QUESTION_COLUMNS} FROM dp_questions"));
+    push_question_filter(&mut query, filter);
+
+    if let Some(after) = cursor.after {
+        query
+            .push(format!(
+                " AND ({columns}) {after_op} (SELECT {columns} FROM dp_questions WHERE question_id = "
+            ))
+            .push_bind(after)
+            .push(")");
+    }
+    if let Some(before) = cursor.before {
+        query
+            .push(format!(
+                " AND ({columns}) {before_op} (SELECT {columns} FROM dp_questions WHERE question_id = "
+            ))
+            .push_bind(before)
+            .push(")");
+    }
+
+    query.push(" ORDER BY ");
+    let direction = if descending { " DESC" } else { " ASC" };
+    let mut separated = query.separated(", ");
+    for column in columns.split(", ") {
+        separated.push(format!("{column}{direction}"));
+    }
+
+    query.push(" LIMIT ").push_bind(cursor.get_limit() + 1);
+
+    let rows = query.build_query_as::<Question>().fetch_all(conn).await?;
+
+    Ok(Page::from_rows(rows, &cursor))
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn count_questions(
+    conn: impl Executor<'_>,
+    filter: &QuestionFilter,
+) -> Result<i64, Error> {
+    tracing::debug!("Counting questions in database");
+
+    let mut query = sqlx::QueryBuilder::new("SELECT COUNT(*) FROM dp_questions");
+    push_question_filter(&mut query, filter);
+
+    query
+        .build_query_scalar()
+        .fetch_one(conn)
+        .await
+        .map_err(Error::DatabaseError)
 }
 
 #[tracing::instrument(skip(conn))]
@@ -76,6 +202,29 @@ pub async fn get_question(conn: impl Executor<'_>, question_id: i64) -> Result<Q
     })
 }
 
+/// Get the questions with the given IDs. The missing or deleted questions are
+/// omitted, and the order is not preserved.
+#[tracing::instrument(skip(conn))]
+pub async fn get_questions(
+    conn: impl Executor<'_>,
+    question_ids: &[i64],
+) -> Result<Vec<Question>, Error> {
+    tracing::debug!("Getting questions from database");
+
+    sqlx::query_as!(
+        Question,
+        r#"
+        SELECT question_id, schema_id, type AS question_type, difficulty AS "difficulty: _", title, description, created_at, updated_at
+        FROM dp_questions
+        WHERE question_id = ANY($1) AND deleted_at IS NULL
+        "#,
+        question_ids,
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
 #[tracing::instrument(skip(conn))]
 pub async fn get_question_answer(
     conn: impl Executor<'_>,
@@ -153,7 +302,9 @@ pub async fn get_question_schema_initial_sql(
         SELECT initial_sql
         FROM dp_questions
         JOIN dp_schemas USING (schema_id)
-        WHERE question_id = $1 AND dp_questions.deleted_at IS NULL
+        WHERE question_id = $1
+            AND dp_questions.deleted_at IS NULL
+            AND dp_schemas.deleted_at IS NULL
         "#,
         question_id
     )
@@ -168,3 +319,234 @@ pub async fn get_question_schema_initial_sql(
         e => Error::DatabaseError(e),
     })
 }
+
+pub struct QuestionCreateParameter<'a> {
+    pub schema_id: Option<&'a str>,
+    pub question_type: &'a str,
+    pub difficulty: Difficulty,
+    pub title: &'a str,
+    pub description: Option<&'a str>,
+    pub answer: &'a str,
+    pub solution_video: Option<&'a str>,
+}
+
+#[tracing::instrument(skip(conn, answer))]
+pub async fn create_question(
+    conn: impl Executor<'_>,
+    QuestionCreateParameter {
+        schema_id,
+        question_type,
+        difficulty,
+        title,
+        description,
+        answer,
+        solution_video,
+    }: QuestionCreateParameter<'_>,
+) -> Result<i64, Error> {
+    tracing::debug!("Creating question");
+
+    let question_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO dp_questions (schema_id, type, difficulty, title, description, answer, solution_video)
+        SELECT $1::text, $2, $3, $4, $5, $6, $7
+        WHERE $1::text IS NULL OR EXISTS (
+            SELECT 1 FROM dp_schemas WHERE schema_id = $1 AND deleted_at IS NULL
+        )
+        RETURNING question_id
+        "#,
+        schema_id,
+        question_type,
+        difficulty as Difficulty,
+        title,
+        description.unwrap_or(""),
+        answer,
+        solution_video,
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| map_schema_violation(e, schema_id))?;
+
+    // Nothing is inserted if the schema is deleted.
+    question_id.ok_or_else(|| Error::NotFound {
+        entity: "schema",
+        id: schema_id.unwrap_or_default().into(),
+    })
+}
+
+/// The fields to update in a question. `None` leaves the field unchanged.
+#[derive(Default)]
+pub struct QuestionUpdateParameter<'a> {
+    /// `Some(None)` detaches the question from its schema.
+    pub schema_id: Option<Option<&'a str>>,
+    pub question_type: Option<&'a str>,
+    pub difficulty: Option<Difficulty>,
+    pub title: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub answer: Option<&'a str>,
+    /// `Some(None)` removes the solution video.
+    pub solution_video: Option<Option<&'a str>>,
+}
+
+#[tracing::instrument(skip(conn, answer))]
+pub async fn update_question(
+    conn: impl Executor<'_>,
+    question_id: i64,
+    QuestionUpdateParameter {
+        schema_id,
+        question_type,
+        difficulty,
+        title,
+        description,
+        answer,
+        solution_video,
+    }: QuestionUpdateParameter<'_>,
+) -> Result<(), Error> {
+    tracing::debug!("Updating question");
+
+    if question_id < 0 {
+        return Err(Error::NotPositiveID);
+    }
+
+    let record = sqlx::query!(
+        r#"
+        WITH updated AS (
+            UPDATE dp_questions
+            SET schema_id = CASE WHEN $2 THEN $3 ELSE schema_id END,
+            type = COALESCE($4, type),
+            difficulty = COALESCE($5, difficulty),
+            title = COALESCE($6, title),
+            description = COALESCE($7, description),
+            answer = COALESCE($8, answer),
+            solution_video = CASE WHEN $9 THEN $10 ELSE solution_video END
+            WHERE question_id = $1 AND deleted_at IS NULL AND (
+                NOT $2 OR $3::text IS NULL OR EXISTS (
+                    SELECT 1 FROM dp_schemas WHERE schema_id = $3 AND deleted_at IS NULL
+                )
+            )
+            RETURNING question_id
+        )
+        SELECT
+            EXISTS (SELECT 1 FROM updated) AS "updated!",
+            EXISTS (
+                SELECT 1 FROM dp_questions WHERE question_id = $1 AND deleted_at IS NULL
+            ) AS "exists!"
+        "#,
+        question_id,
+        schema_id.is_some(),
+        schema_id.flatten(),
+        question_type,
+        difficulty as Option<Difficulty>,
+        title,
+        description,
+        answer,
+        solution_video.is_some(),
+        solution_video.flatten(),
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(|e| map_schema_violation(e, schema_id.flatten()))?;
+
+    match (record.updated, record.exists) {
+        (true, _) => Ok(()),
+        // The question exists, so it is the schema which is deleted.
+        (false, true) => Err(Error::NotFound {
+            entity: "schema",
+            id: schema_id.flatten().unwrap_or_default().into(),
+        }),
+        (false, false) => Err(Error::NotFound {
+            entity: "question",
+            id: ecow::eco_format!("{question_id}"),
+        }),
+    }
+}
+
+/// Mark the question as deleted.
+#[tracing::instrument(skip(conn))]
+pub async fn delete_question(conn: impl Executor<'_>, question_id: i64) -> Result<(), Error> {
+    tracing::debug!("Deleting question");
+
+    if question_id < 0 {
+        return Err(Error::NotPositiveID);
+    }
+
+    let affected_rows = sqlx::query!(
+        r#"
+        UPDATE dp_questions
+        SET deleted_at = now()
+        WHERE question_id = $1 AND deleted_at IS NULL
+        "#,
+        question_id,
+    )
+    .execute(conn)
+    .await?
+    .rows_affected();
+
+    if affected_rows == 0 {
+        return Err(Error::NotFound {
+            entity: "question",
+            id: ecow::eco_format!("{question_id}"),
+        });
+    }
+
+    Ok(())
+}
+
+/// Restore a question marked as deleted.
+///
+/// Returns [`Error::NotFound`] for the schema if the schema of the question is
+/// deleted, which should be restored first.
+#[tracing::instrument(skip(conn))]
+pub async fn restore_question(conn: impl Executor<'_>, question_id: i64) -> Result<(), Error> {
+    tracing::debug!("Restoring question");
+
+    if question_id < 0 {
+        return Err(Error::NotPositiveID);
+    }
+
+    let record = sqlx::query!(
+        r#"
+        WITH target AS (
+            SELECT question_id, schema_id, schema_id IS NOT NULL AND NOT EXISTS (
+                SELECT 1 FROM dp_schemas
+                WHERE dp_schemas.schema_id = dp_questions.schema_id
+                    AND dp_schemas.deleted_at IS NULL
+            ) AS schema_deleted
+            FROM dp_questions
+            WHERE question_id = $1 AND deleted_at IS NOT NULL
+        ), restored AS (
+            UPDATE dp_questions
+            SET deleted_at = NULL
+            WHERE question_id IN (SELECT question_id FROM target WHERE NOT schema_deleted)
+        )
+        SELECT schema_id, schema_deleted AS "schema_deleted!" FROM target
+        "#,
+        question_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    match record {
+        None => Err(Error::NotFound {
+            entity: "deleted question",
+            id: ecow::eco_format!("{question_id}"),
+        }),
+        Some(record) if record.schema_deleted => Err(Error::NotFound {
+            entity: "schema",
+            id: record.schema_id.unwrap_or_default().into(),
+        }),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Turn the foreign key violation on `schema_id` into a [`Error::NotFound`].
+fn map_schema_violation(e: sqlx::Error, schema_id: Option<&str>) -> Error {
+    match (e, schema_id) {
+        (sqlx::Error::Database(e), Some(schema_id)) if e.is_foreign_key_violation() => {
+            Error::NotFound {
+                entity: "schema",
+                id: schema_id.into(),
+            }
+        }
+        (e, _) => Error::DatabaseError(e),
+    }
+}
@@ -1,6 +1,9 @@
 //! Schema-related database operations.
 
-use super::{Error, Executor};
+use super::{
+    Error, Executor,
+    cursor::{KeysetCursor, Page},
+};
 use chrono::Utc;
 
 #[derive(Debug, Clone)]
@@ -36,6 +39,29 @@ pub async fn get_schema(conn: impl Executor<'_>, schema_id: &str) -> Result<Sche
     })
 }
 
+/// Get the schemas with the given IDs. The missing or deleted schemas are
+/// omitted, and the order is not preserved.
+#[tracing::instrument(skip(conn))]
+pub async fn get_schemas(
+    conn: impl Executor<'_>,
+    schema_ids: &[String],
+) -> Result<Vec<Schema>, Error> {
+    tracing::debug!("Getting schemas from database");
+
+    sqlx::query_as!(
+        Schema,
+        r#"
+        SELECT schema_id, picture, description, created_at, updated_at
+        FROM dp_schemas
+        WHERE schema_id = ANY($1) AND deleted_at IS NULL
+        "#,
+        schema_ids,
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
 #[tracing::instrument(skip(conn))]
 pub async fn get_schema_initial_sql(
     conn: impl Executor<'_>,
@@ -62,3 +88,228 @@ pub async fn get_schema_initial_sql(
         e => Error::DatabaseError(e),
     })
 }
+
+/// List the schemas ordered by their ID with keyset pagination.
+#[tracing::instrument(skip(conn))]
+pub async fn list_schemas(
+    conn: impl Executor<'_>,
+    cursor: KeysetCursor<String>,
+) -> Result<Page<Schema>, Error> {
+    tracing::debug!("Listing schemas from database");
+
+    let rows = if cursor.is_backward() {
+        sqlx::query_as!(
+            Schema,
+            r#"
+            SELECT schema_id, picture, description, created_at, updated_at
+            FROM dp_schemas
+            WHERE deleted_at IS NULL
+                AND ($1::text IS NULL OR schema_id > $1)
+                AND ($2::text IS NULL OR schema_id < $2)
+            ORDER BY schema_id DESC
+            LIMIT $3;
+            "#,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    } else {
+        sqlx::query_as!(
+            Schema,
+            r#"
+            SELECT schema_id, picture, description, created_at, updated_at
+            FROM dp_schemas
+            WHERE deleted_at IS NULL
+                AND ($1::text IS NULL OR schema_id > $1)
+                AND ($2::text IS NULL OR schema_id < $2)
+            ORDER BY schema_id
+            LIMIT $3;
+            "#,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    };
+
+    Ok(Page::from_rows(rows, &cursor))
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn count_schemas(conn: impl Executor<'_>) -> Result<i64, Error> {
+    tracing::debug!("Counting schemas in database");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM dp_schemas
+        WHERE deleted_at IS NULL;
+        "#
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+pub struct SchemaCreateParameter<'a> {
+    pub schema_id: &'a str,
+    pub picture: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub initial_sql: &'a str,
+}
+
+#[tracing::instrument(skip(conn, initial_sql))]
+pub async fn create_schema(
+    conn: impl Executor<'_>,
+    SchemaCreateParameter {
+        schema_id,
+        picture,
+        description,
+        initial_sql,
+    }: SchemaCreateParameter<'_>,
+) -> Result<(), Error> {
+    tracing::debug!("Creating schema");
+
+    sqlx::query!(
+        r#"
+        INSERT INTO dp_schemas (schema_id, picture, description, initial_sql)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        schema_id,
+        picture,
+        description.unwrap_or(""),
+        initial_sql,
+    )
+    .execute(conn)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(e) if e.is_unique_violation() => Error::AlreadyExists {
+            entity: "schema",
+            id: schema_id.into(),
+        },
+        e => Error::DatabaseError(e),
+    })?;
+
+    Ok(())
+}
+
+/// The fields to update in a schema. `None` leaves the field unchanged.
+#[derive(Default)]
+pub struct SchemaUpdateParameter<'a> {
+    /// `Some(None)` removes the picture.
+    pub picture: Option<Option<&'a str>>,
+    pub description: Option<&'a str>,
+    pub initial_sql: Option<&'a str>,
+}
+
+#[tracing::instrument(skip(conn, initial_sql))]
+pub async fn update_schema(
+    conn: impl Executor<'_>,
+    schema_id: &str,
+    SchemaUpdateParameter {
+        picture,
+        description,
+        initial_sql,
+    }: SchemaUpdateParameter<'_>,
+) -> Result<(), Error> {
+    tracing::debug!("Updating schema");
+
+    let affected_rows = sqlx::query!(
+        r#"
+        UPDATE dp_schemas
+        SET picture = CASE WHEN $2 THEN $3 ELSE picture END,
+            description = COALESCE($4, description),
+            initial_sql = COALESCE($5, initial_sql)
+        WHERE schema_id = $1 AND deleted_at IS NULL
+        "#,
+        schema_id,
+        picture.is_some(),
+        picture.flatten(),
+        description,
+        initial_sql,
+    )
+    .execute(conn)
+    .await?
+    .rows_affected();
+
+    if affected_rows == 0 {
+        return Err(Error::NotFound {
+            entity: "schema",
+            id: schema_id.into(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Mark the schema as deleted.
+///
+/// Returns [`Error::InUse`] if the schema is still used by a question which
+/// is not deleted.
+#[tracing::instrument(skip(conn))]
+pub async fn delete_schema(conn: impl Executor<'_>, schema_id: &str) -> Result<(), Error> {
+    tracing::debug!("Deleting schema");
+
+    let in_use = sqlx::query_scalar!(
+        r#"
+        WITH target AS (
+            SELECT schema_id, EXISTS (
+                SELECT 1 FROM dp_questions
+                WHERE schema_id = $1 AND deleted_at IS NULL
+            ) AS in_use
+            FROM dp_schemas
+            WHERE schema_id = $1 AND deleted_at IS NULL
+        ), deleted AS (
+            UPDATE dp_schemas
+            SET deleted_at = now()
+            WHERE schema_id IN (SELECT schema_id FROM target WHERE NOT in_use)
+        )
+        SELECT in_use AS "in_use!" FROM target
+        "#,
+        schema_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    match in_use {
+        None => Err(Error::NotFound {
+            entity: "schema",
+            id: schema_id.into(),
+        }),
+        Some(true) => Err(Error::InUse {
+            entity: "schema",
+            id: schema_id.into(),
+        }),
+        Some(false) => Ok(()),
+    }
+}
+
+/// Restore a schema marked as deleted.
+#[tracing::instrument(skip(conn))]
+pub async fn restore_schema(conn: impl Executor<'_>, schema_id: &str) -> Result<(), Error> {
+    tracing::debug!("Restoring schema");
+
+    let affected_rows = sqlx::query!(
+        r#"
+        UPDATE dp_schemas
+        SET deleted_at = NULL
+        WHERE schema_id = $1 AND deleted_at IS NOT NULL
+        "#,
+        schema_id,
+    )
+    .execute(conn)
+    .await?
+    .rows_affected();
+
+    if affected_rows == 0 {
+        return Err(Error::NotFound {
+            entity: "deleted schema",
+            id: schema_id.into(),
+        });
+    }
+
+    Ok(())
+}
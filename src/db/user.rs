@@ -1,11 +1,24 @@
 use ecow::eco_format;
 
-use super::{Acquire, Error, Executor};
+use super::{
+    Acquire, Error, Executor,
+    cursor::{KeysetCursor, Page},
+};
+
+/// The role of a user, from the least to the most privileged.
+#[derive(Debug, Clone, Copy, sqlx::Type, PartialEq, Eq, PartialOrd, Ord)]
+#[sqlx(type_name = "dp_user_role", rename_all = "lowercase")]
+pub enum UserRole {
+    Student,
+    Teacher,
+    Admin,
+}
 
 #[derive(Debug, Clone)]
 pub struct User {
     pub user_id: String,
     pub group_id: Option<i64>,
+    pub role: UserRole,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
@@ -20,7 +33,7 @@ pub async fn get_or_initialize_user(conn: impl Acquire<'_>, user_id: &str) -> Re
     let user_info = sqlx::query_as!(
         User,
         r#"
-        SELECT user_id, group_id, created_at, updated_at, deleted_at
+        SELECT user_id, group_id, role AS "role: UserRole", created_at, updated_at, deleted_at
         FROM dp_users
         WHERE user_id = $1
         "#,
@@ -44,7 +57,7 @@ pub async fn get_or_initialize_user(conn: impl Acquire<'_>, user_id: &str) -> Re
         r#"
         INSERT INTO dp_users (user_id)
         VALUES ($1)
-        RETURNING user_id, group_id, created_at, updated_at, deleted_at
+        RETURNING user_id, group_id, role AS "role: UserRole", created_at, updated_at, deleted_at
         "#,
         user_id,
     )
@@ -54,6 +67,26 @@ pub async fn get_or_initialize_user(conn: impl Acquire<'_>, user_id: &str) -> Re
     Ok(created_user_info)
 }
 
+/// Get the user, including the deleted one, or `None` if the user has not
+/// been initialized.
+#[tracing::instrument(skip(conn))]
+pub async fn get_user(conn: impl Executor<'_>, user_id: &str) -> Result<Option<User>, Error> {
+    tracing::debug!("Getting user from database");
+
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT user_id, group_id, role AS "role: UserRole", created_at, updated_at, deleted_at
+        FROM dp_users
+        WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
 /// Mark the user as deleted.
 ///
 /// You should also remove this user from the authentication service.
@@ -82,26 +115,146 @@ pub async fn delete_user(conn: impl Executor<'_>, user_id: &str) -> Result<(), E
     Ok(())
 }
 
+/// Get the stored role of the user, or `None` if the user has not been
+/// initialized or has been deleted.
+#[tracing::instrument(skip(conn))]
+pub async fn get_user_role(
+    conn: impl Executor<'_>,
+    user_id: &str,
+) -> Result<Option<UserRole>, Error> {
+    tracing::debug!("Getting the role of user from database");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT role AS "role: UserRole"
+        FROM dp_users
+        WHERE user_id = $1 AND deleted_at IS NULL
+        "#,
+        user_id,
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+/// Change the role of the user.
+///
+/// The user is initialized if they have not logged in yet, so that a role
+/// can be granted in advance.
+#[tracing::instrument(skip(conn))]
+pub async fn set_user_role(
+    conn: impl Executor<'_>,
+    user_id: &str,
+    role: UserRole,
+) -> Result<User, Error> {
+    tracing::debug!("Setting the role of user");
+
+    sqlx::query_as!(
+        User,
+        r#"
+        INSERT INTO dp_users (user_id, role)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET role = EXCLUDED.role
+        WHERE dp_users.deleted_at IS NULL
+        RETURNING user_id, group_id, role AS "role: UserRole", created_at, updated_at, deleted_at
+        "#,
+        user_id,
+        role as UserRole,
+    )
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| Error::NotFound {
+        entity: "user",
+        id: eco_format!("{user_id}"),
+    })
+}
+
+/// Whether the user is a member of a group managed by `owner_id`.
+#[tracing::instrument(skip(conn))]
+pub async fn is_user_in_owned_group(
+    conn: impl Executor<'_>,
+    owner_id: &str,
+    user_id: &str,
+) -> Result<bool, Error> {
+    tracing::debug!("Checking the owner of the group of user");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM dp_users u
+            JOIN dp_groups g ON g.group_id = u.group_id
+            WHERE u.user_id = $1 AND u.deleted_at IS NULL
+                AND g.owner_id = $2 AND g.deleted_at IS NULL
+        ) AS "exists!"
+        "#,
+        user_id,
+        owner_id,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+/// Whether `owner_id` can move the user into their groups, that is, the user is
+/// a student who is not in a group or in a group managed by `owner_id`.
+///
+/// Only the stored role of the user is checked, as the roles granted by the
+/// token claims of the other users are not known.
+#[tracing::instrument(skip(conn))]
+pub async fn can_enroll_user(
+    conn: impl Executor<'_>,
+    owner_id: &str,
+    user_id: &str,
+) -> Result<bool, Error> {
+    tracing::debug!("Checking if the user can be enrolled");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT NOT EXISTS (
+            SELECT 1
+            FROM dp_users u
+            LEFT JOIN dp_groups g ON g.group_id = u.group_id
+            WHERE u.user_id = $1
+                AND (u.role <> 'student' OR (u.group_id IS NOT NULL AND g.owner_id IS DISTINCT FROM $2))
+        ) AS "enrollable!"
+        "#,
+        user_id,
+        owner_id,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
 pub struct GroupCreateParameter<'a> {
     pub name: &'a str,
     pub description: Option<&'a str>,
+    /// The teacher who manages the group. The user must exist.
+    pub owner_id: Option<&'a str>,
 }
 
 #[tracing::instrument(skip(conn))]
 pub async fn create_group(
     conn: impl Executor<'_>,
-    GroupCreateParameter { name, description }: GroupCreateParameter<'_>,
+    GroupCreateParameter {
+        name,
+        description,
+        owner_id,
+    }: GroupCreateParameter<'_>,
 ) -> Result<i64, Error> {
     tracing::debug!("Creating group");
 
     let group_id = sqlx::query!(
         r#"
-        INSERT INTO dp_groups (name, description)
-        VALUES ($1, $2)
+        INSERT INTO dp_groups (name, description, owner_id)
+        VALUES ($1, $2, $3)
         RETURNING group_id
         "#,
         name,
         description.unwrap_or(""),
+        owner_id,
     )
     .fetch_one(conn)
     .await?
@@ -115,6 +268,8 @@ pub struct Group {
     pub group_id: i64,
     pub name: String,
     pub description: String,
+    /// The teacher who manages the group.
+    pub owner_id: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -126,7 +281,7 @@ pub async fn get_group(conn: impl Executor<'_>, group_id: i64) -> Result<Group,
     let group = sqlx::query_as!(
         Group,
         r#"
-        SELECT group_id, name, description, created_at, updated_at
+        SELECT group_id, name, description, owner_id, created_at, updated_at
         FROM dp_groups
         WHERE group_id = $1 AND deleted_at IS NULL
         "#,
@@ -144,3 +299,300 @@ pub async fn get_group(conn: impl Executor<'_>, group_id: i64) -> Result<Group,
 
     Ok(group)
 }
+
+/// Get the groups with the given IDs. The missing or deleted groups are
+/// omitted, and the order is not preserved.
+#[tracing::instrument(skip(conn))]
+pub async fn get_groups(conn: impl Executor<'_>, group_ids: &[i64]) -> Result<Vec<Group>, Error> {
+    tracing::debug!("Getting groups from database");
+
+    sqlx::query_as!(
+        Group,
+        r#"
+        SELECT group_id, name, description, owner_id, created_at, updated_at
+        FROM dp_groups
+        WHERE group_id = ANY($1) AND deleted_at IS NULL
+        "#,
+        group_ids,
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+/// List the groups ordered by their ID with keyset pagination.
+///
+/// If `owner_id` is given, only the groups managed by the user are listed.
+#[tracing::instrument(skip(conn))]
+pub async fn list_groups(
+    conn: impl Executor<'_>,
+    owner_id: Option<&str>,
+    cursor: KeysetCursor<i64>,
+) -> Result<Page<Group>, Error> {
+    tracing::debug!("Listing groups from database");
+
+    let rows = if cursor.is_backward() {
+        sqlx::query_as!(
+            Group,
+            r#"
+            SELECT group_id, name, description, owner_id, created_at, updated_at
+            FROM dp_groups
+            WHERE deleted_at IS NULL
+                AND ($1::text IS NULL OR owner_id = $1)
+                AND ($2::bigint IS NULL OR group_id > $2)
+                AND ($3::bigint IS NULL OR group_id < $3)
+            ORDER BY group_id DESC
+            LIMIT $4
+            "#,
+            owner_id,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    } else {
+        sqlx::query_as!(
+            Group,
+            r#"
+            SELECT group_id, name, description, owner_id, created_at, updated_at
+            FROM dp_groups
+            WHERE deleted_at IS NULL
+                AND ($1::text IS NULL OR owner_id = $1)
+                AND ($2::bigint IS NULL OR group_id > $2)
+                AND ($3::bigint IS NULL OR group_id < $3)
+            ORDER BY group_id
+            LIMIT $4
+            "#,
+            owner_id,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    };
+
+    Ok(Page::from_rows(rows, &cursor))
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn count_groups(conn: impl Executor<'_>, owner_id: Option<&str>) -> Result<i64, Error> {
+    tracing::debug!("Counting groups in database");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM dp_groups
+        WHERE deleted_at IS NULL AND ($1::text IS NULL OR owner_id = $1)
+        "#,
+        owner_id,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
+
+/// The fields to update in a group. `None` leaves the field unchanged.
+#[derive(Default)]
+pub struct GroupUpdateParameter<'a> {
+    pub name: Option<&'a str>,
+    pub description: Option<&'a str>,
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn update_group(
+    conn: impl Executor<'_>,
+    group_id: i64,
+    GroupUpdateParameter { name, description }: GroupUpdateParameter<'_>,
+) -> Result<(), Error> {
+    tracing::debug!("Updating group");
+
+    let affected_rows = sqlx::query!(
+        r#"
+        UPDATE dp_groups
+        SET name = COALESCE($2, name),
+            description = COALESCE($3, description)
+        WHERE group_id = $1 AND deleted_at IS NULL
+        "#,
+        group_id,
+        name,
+        description,
+    )
+    .execute(conn)
+    .await?
+    .rows_affected();
+
+    if affected_rows == 0 {
+        return Err(Error::NotFound {
+            entity: "group",
+            id: eco_format!("{group_id}"),
+        });
+    }
+
+    Ok(())
+}
+
+/// Mark the group as deleted, and remove all the members from it.
+#[tracing::instrument(skip(conn))]
+pub async fn delete_group(conn: impl Acquire<'_>, group_id: i64) -> Result<(), Error> {
+    tracing::debug!("Deleting group");
+
+    let mut tx = conn.begin().await?;
+
+    let affected_rows = sqlx::query!(
+        r#"
+        UPDATE dp_groups
+        SET deleted_at = now()
+        WHERE group_id = $1 AND deleted_at IS NULL
+        "#,
+        group_id,
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if affected_rows == 0 {
+        return Err(Error::NotFound {
+            entity: "group",
+            id: eco_format!("{group_id}"),
+        });
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE dp_users
+        SET group_id = NULL
+        WHERE group_id = $1
+        "#,
+        group_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Move the user into the group, or out of any group if `group_id` is `None`.
+///
+/// When moving into a group, the user is initialized if they have not logged
+/// in yet, so that a user can be assigned to a group in advance. Removing an
+/// unknown user from their group fails with [`Error::NotFound`].
+#[tracing::instrument(skip(conn))]
+pub async fn set_user_group(
+    conn: impl Executor<'_>,
+    user_id: &str,
+    group_id: Option<i64>,
+) -> Result<User, Error> {
+    tracing::debug!("Setting the group of user");
+
+    let Some(group_id) = group_id else {
+        return sqlx::query_as!(
+            User,
+            r#"
+            UPDATE dp_users
+            SET group_id = NULL
+            WHERE user_id = $1 AND deleted_at IS NULL
+            RETURNING user_id, group_id, role AS "role: UserRole", created_at, updated_at, deleted_at
+            "#,
+            user_id,
+        )
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| Error::NotFound {
+            entity: "user",
+            id: eco_format!("{user_id}"),
+        });
+    };
+
+    sqlx::query_as!(
+        User,
+        r#"
+        INSERT INTO dp_users (user_id, group_id)
+        SELECT $1, $2
+        WHERE EXISTS (SELECT 1 FROM dp_groups WHERE group_id = $2 AND deleted_at IS NULL)
+        ON CONFLICT (user_id) DO UPDATE
+        SET group_id = EXCLUDED.group_id
+        WHERE dp_users.deleted_at IS NULL
+        RETURNING user_id, group_id, role AS "role: UserRole", created_at, updated_at, deleted_at
+        "#,
+        user_id,
+        group_id,
+    )
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| Error::NotFound {
+        entity: "group or user",
+        id: eco_format!("{group_id}, {user_id}"),
+    })
+}
+
+/// List the members of a group ordered by their ID with keyset pagination.
+#[tracing::instrument(skip(conn))]
+pub async fn list_group_members(
+    conn: impl Executor<'_>,
+    group_id: i64,
+    cursor: KeysetCursor<String>,
+) -> Result<Page<User>, Error> {
+    tracing::debug!("Listing group members from database");
+
+    let rows = if cursor.is_backward() {
+        sqlx::query_as!(
+            User,
+            r#"
+            SELECT user_id, group_id, role AS "role: UserRole", created_at, updated_at, deleted_at
+            FROM dp_users
+            WHERE group_id = $1 AND deleted_at IS NULL
+                AND ($2::text IS NULL OR user_id > $2)
+                AND ($3::text IS NULL OR user_id < $3)
+            ORDER BY user_id DESC
+            LIMIT $4
+            "#,
+            group_id,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    } else {
+        sqlx::query_as!(
+            User,
+            r#"
+            SELECT user_id, group_id, role AS "role: UserRole", created_at, updated_at, deleted_at
+            FROM dp_users
+            WHERE group_id = $1 AND deleted_at IS NULL
+                AND ($2::text IS NULL OR user_id > $2)
+                AND ($3::text IS NULL OR user_id < $3)
+            ORDER BY user_id
+            LIMIT $4
+            "#,
+            group_id,
+            cursor.after,
+            cursor.before,
+            cursor.get_limit() + 1,
+        )
+        .fetch_all(conn)
+        .await?
+    };
+
+    Ok(Page::from_rows(rows, &cursor))
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn count_group_members(conn: impl Executor<'_>, group_id: i64) -> Result<i64, Error> {
+    tracing::debug!("Counting group members in database");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM dp_users
+        WHERE group_id = $1 AND deleted_at IS NULL
+        "#,
+        group_id,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::DatabaseError)
+}
@@ -1,21 +1,47 @@
 //! GraphQL schemas.
 
+pub mod account;
+pub mod api_token;
 pub mod auth;
 pub mod error;
+pub mod event;
+pub mod group;
+pub mod guard;
+pub mod loader;
+pub mod metrics;
 pub mod poem;
+pub mod progress;
 pub mod questions;
+pub mod rate_limit;
 pub mod schema;
 pub mod sql_executor;
 pub mod user;
+pub mod user_cache;
 
-use async_graphql::MergedObject;
+use async_graphql::{MergedObject, MergedSubscription};
 
 #[derive(MergedObject, Default)]
 pub struct Query(
     pub schema::SchemaQuery,
     pub questions::QuestionQuery,
     pub user::UserQuery,
+    pub group::GroupQuery,
+    pub api_token::ApiTokenQuery,
+    pub account::AccountQuery,
 );
 
 #[derive(MergedObject, Default)]
-pub struct Mutation(pub sql_executor::SqlExecutorMutation);
+pub struct Mutation(
+    pub sql_executor::SqlExecutorMutation,
+    pub questions::QuestionMutation,
+    pub schema::SchemaMutation,
+    pub group::GroupMutation,
+    pub user::UserMutation,
+    pub api_token::ApiTokenMutation,
+    pub account::AccountMutation,
+);
+
+#[derive(MergedSubscription, Default)]
+pub struct Subscription(pub sql_executor::SqlExecutorSubscription);
+
+pub type Schema = async_graphql::Schema<Query, Mutation, Subscription>;
@@ -0,0 +1,118 @@
+use async_graphql::{Context, Json, Object, Result};
+use serde_json::{Value, json};
+
+use crate::{db, logto::LogtoClient};
+
+use super::{
+    auth::{ContextAuthExt, Role},
+    error,
+    guard::InteractiveGuard,
+    user_cache::UserCache,
+};
+
+#[derive(Default)]
+pub struct AccountQuery;
+
+#[Object]
+impl AccountQuery {
+    /// Export the profile, the attempts and the viewed solutions of the
+    /// current user as a JSON document. Only a login is required, as the
+    /// users can always access their own data.
+    async fn export_my_data<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Json<Value>> {
+        tracing::debug!("Running GraphQL query 'export_my_data'");
+
+        let sub = ctx.require_sub()?;
+        let pool = ctx.data::<db::Pool>()?;
+
+        let user = db::get_or_initialize_user(pool, sub)
+            .await
+            .map_err(error::gqlize)?;
+        let attempts = db::list_all_attempt_events(pool, sub)
+            .await
+            .map_err(error::gqlize)?;
+        let solution_views = db::list_all_solution_events(pool, sub)
+            .await
+            .map_err(error::gqlize)?;
+
+        let attempts = attempts
+            .into_iter()
+            .map(|event| {
+                json!({
+                    "id": event.attempt_event_id,
+                    "questionId": event.question_id,
+                    "query": event.query,
+                    "status": attempt_status(event.status),
+                    "error": event.error,
+                    "createdAt": event.created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        let solution_views = solution_views
+            .into_iter()
+            .map(|event| {
+                json!({
+                    "id": event.solution_event_id,
+                    "questionId": event.question_id,
+                    "createdAt": event.created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "profile": {
+                "userId": user.user_id,
+                "groupId": user.group_id,
+                "role": Role::from(user.role).as_str(),
+                "createdAt": user.created_at,
+                "updatedAt": user.updated_at,
+            },
+            "attempts": attempts,
+            "solutionViews": solution_views,
+            "exportedAt": chrono::Utc::now(),
+        })))
+    }
+}
+
+#[derive(Default)]
+pub struct AccountMutation;
+
+#[Object]
+impl AccountMutation {
+    /// Delete the account of the current user.
+    ///
+    /// The user is removed from Logto if its Management API is configured,
+    /// and the data in the database is marked as deleted. It cannot be done
+    /// with an API token.
+    #[graphql(guard = "InteractiveGuard")]
+    async fn delete_my_account<'ctx>(&self, ctx: &Context<'ctx>) -> Result<bool> {
+        tracing::debug!("Running GraphQL mutation 'delete_my_account'");
+
+        let sub = ctx.require_sub()?;
+        let pool = ctx.data::<db::Pool>()?;
+        let logto = ctx.data::<Option<LogtoClient>>()?;
+
+        // The user may not have been initialized, which delete_user requires.
+        db::get_or_initialize_user(pool, sub)
+            .await
+            .map_err(error::gqlize)?;
+
+        // Remove the user from Logto first, so that a failure can be retried.
+        match logto {
+            Some(logto) => logto.delete_user(sub).await.map_err(error::gqlize)?,
+            None => tracing::warn!("Logto is not configured. Only deleting the user locally."),
+        }
+
+        db::delete_user(pool, sub).await.map_err(error::gqlize)?;
+        ctx.data::<UserCache>()?.invalidate(sub);
+
+        Ok(true)
+    }
+}
+
+fn attempt_status(status: db::AttemptStatus) -> &'static str {
+    match status {
+        db::AttemptStatus::Pending => "pending",
+        db::AttemptStatus::Passed => "passed",
+        db::AttemptStatus::Failed => "failed",
+    }
+}
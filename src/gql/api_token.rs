@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::{DateTime, Utc};
+use ecow::EcoString;
+
+use crate::db;
+
+use super::{
+    auth::{Auth, ContextAuthExt, Scope, api_token},
+    error,
+    guard::InteractiveGuard,
+};
+
+#[derive(Default)]
+pub struct ApiTokenQuery;
+
+#[Object]
+impl ApiTokenQuery {
+    /// List the API tokens of the current user which have not been revoked.
+    async fn api_tokens<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<ApiToken>> {
+        tracing::debug!("Running GraphQL query 'api_tokens'");
+
+        let sub = ctx.require_sub()?;
+        let pool = ctx.data::<db::Pool>()?;
+
+        let tokens = db::list_api_tokens(pool, sub)
+            .await
+            .map_err(error::gqlize)?;
+        Ok(tokens.into_iter().map(Into::into).collect())
+    }
+}
+
+#[derive(Default)]
+pub struct ApiTokenMutation;
+
+#[Object]
+impl ApiTokenMutation {
+    /// Create an API token for the scripts. Pass it as a bearer token in the
+    /// `Authorization` header, like a JWT.
+    ///
+    /// The token can only be granted the scopes the current credential has,
+    /// and the plaintext is only returned here. An API token cannot create
+    /// another one.
+    #[graphql(guard = "InteractiveGuard")]
+    async fn create_api_token<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        name: String,
+        scopes: Vec<Scope>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<CreatedApiToken> {
+        tracing::debug!("Running GraphQL mutation 'create_api_token'");
+
+        let sub = ctx.require_sub()?;
+        let auth = ctx.data::<Auth>()?;
+        let pool = ctx.data::<db::Pool>()?;
+
+        if let Some(scope) = scopes.iter().find(|scope| !auth.has_scope(**scope)) {
+            return Err(invalid_token_request(format!(
+                "{scope} cannot be granted as you do not have it"
+            )));
+        }
+        if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(invalid_token_request(
+                "The token must expire in the future.",
+            ));
+        }
+
+        // The token refers to the user, who may not have been initialized.
+        db::get_or_initialize_user(pool, sub)
+            .await
+            .map_err(error::gqlize)?;
+
+        let (token, token_hash) = api_token::generate();
+        let scopes = scopes
+            .iter()
+            .map(|scope| scope.as_str().to_string())
+            .collect::<Vec<_>>();
+
+        let api_token = db::create_api_token(
+            pool,
+            db::ApiTokenCreateParameter {
+                user_id: sub,
+                name: &name,
+                token_hash: &token_hash,
+                scopes: &scopes,
+                expires_at,
+            },
+        )
+        .await
+        .map_err(error::gqlize)?;
+
+        Ok(CreatedApiToken {
+            token,
+            api_token: api_token.into(),
+        })
+    }
+
+    /// Revoke an API token of the current user.
+    #[graphql(guard = "InteractiveGuard")]
+    async fn revoke_api_token<'ctx>(&self, ctx: &Context<'ctx>, id: i64) -> Result<bool> {
+        tracing::debug!("Running GraphQL mutation 'revoke_api_token'");
+
+        let sub = ctx.require_sub()?;
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::revoke_api_token(pool, sub, id)
+            .await
+            .map_err(error::gqlize)?;
+        Ok(true)
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// The token never expires if it is `null`.
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<db::ApiToken> for ApiToken {
+    fn from(token: db::ApiToken) -> Self {
+        Self {
+            id: token.api_token_id,
+            name: token.name,
+            scopes: token
+                .scopes
+                .iter()
+                .filter_map(|scope| Scope::parse(scope))
+                .collect(),
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct CreatedApiToken {
+    /// The plaintext of the token. It cannot be retrieved again.
+    pub token: String,
+    pub api_token: ApiToken,
+}
+
+fn invalid_token_request(details: impl Into<Cow<'static, str>>) -> async_graphql::Error {
+    error::Error {
+        code: error::ErrorCode::Unauthorized,
+        title: EcoString::inline("Unauthorized"),
+        details: details.into(),
+        error: None,
+    }
+    .to_gql_error()
+}
@@ -1,12 +1,18 @@
-use std::collections::HashSet;
+pub mod api_token;
+pub mod provider;
 
-use async_graphql::Context;
-use cached::proc_macro::once;
+pub use provider::*;
+
+use std::{collections::HashSet, sync::Arc};
+
+use async_graphql::{Context, Enum};
 use ecow::EcoString;
-use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
-use reqwest::Url;
+use jsonwebtoken::Algorithm;
+use tokio::sync::OnceCell;
+
+use crate::db;
 
-#[derive(Clone, Copy)]
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
 pub enum Scope {
     /// Allow reading public resources (schema, questions, etc.)
     ReadPublicResource,
@@ -16,6 +22,10 @@ pub enum Scope {
     ReadSolution,
     /// Allow executing the SQL statement.
     Execution,
+    /// Allow creating, updating and deleting resources (schema, questions, etc.)
+    WriteResource,
+    /// Allow managing the users and their groups.
+    ManageUser,
 }
 
 impl std::fmt::Display for Scope {
@@ -37,28 +47,117 @@ impl Scope {
             Scope::ReadAnswer => "read:answer",
             Scope::ReadSolution => "read:solution",
             Scope::Execution => "execution",
+            Scope::WriteResource => "write:resource",
+            Scope::ManageUser => "manage:user",
+        }
+    }
+
+    /// Parse a scope from its string form. The unknown scopes are ignored.
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "read:public_resource" => Some(Scope::ReadPublicResource),
+            "read:answer" => Some(Scope::ReadAnswer),
+            "read:solution" => Some(Scope::ReadSolution),
+            "execution" => Some(Scope::Execution),
+            "write:resource" => Some(Scope::WriteResource),
+            "manage:user" => Some(Scope::ManageUser),
+            _ => None,
+        }
+    }
+}
+
+/// The role of a user, from the least to the most privileged.
+///
+/// Teachers manage their own groups, and admins manage everything, including
+/// the questions and schemas shared by all the groups.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Student,
+    Teacher,
+    Admin,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Role {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::Student => "student",
+            Role::Teacher => "teacher",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Parse a role from the token claims. The unknown roles are ignored.
+    pub fn from_claim(role: &str) -> Option<Self> {
+        match role {
+            "student" => Some(Role::Student),
+            "teacher" => Some(Role::Teacher),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl From<db::UserRole> for Role {
+    fn from(role: db::UserRole) -> Self {
+        match role {
+            db::UserRole::Student => Self::Student,
+            db::UserRole::Teacher => Self::Teacher,
+            db::UserRole::Admin => Self::Admin,
         }
     }
 }
 
+impl From<Role> for db::UserRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Student => Self::Student,
+            Role::Teacher => Self::Teacher,
+            Role::Admin => Self::Admin,
+        }
+    }
+}
+
+/// How the request is authenticated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Credential {
+    /// A JWT issued to the user by an interactive login.
+    #[default]
+    Jwt,
+    /// An API token created by the user for the scripts.
+    ApiToken,
+}
+
+/// Authenticate the requests with the tokens verified by an [`AuthProvider`].
 #[derive(Clone)]
 pub struct AuthBuilder {
-    pub logto_domain: EcoString,
-    pub resource_indicator: EcoString,
+    provider: Arc<dyn AuthProvider>,
 }
 
 impl AuthBuilder {
+    pub fn new(provider: impl AuthProvider + 'static) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+
     pub async fn build(&self, jwt: &str) -> Result<Auth, AuthError> {
         #[derive(Debug, serde::Deserialize)]
         struct Claim {
             scope: Option<String>,
             sub: String,
+            #[serde(default)]
+            roles: Vec<String>,
         }
 
-        let header = jsonwebtoken::decode_header(&jwt).map_err(AuthError::DecodeJwtHeader)?;
-        let key = get_jwk_decoding_key(&self.logto_domain).await?;
-        let validation =
-            get_validation_parameter(&self.logto_domain, &self.resource_indicator, header.alg);
+        let header = jsonwebtoken::decode_header(jwt).map_err(AuthError::DecodeJwtHeader)?;
+        let key = self.provider.decoding_key(&header).await?;
+        let validation = self.provider.validation(header.alg);
 
         let token_data =
             jsonwebtoken::decode::<Claim>(jwt, &key, &validation).map_err(AuthError::DecodeJwt)?;
@@ -68,9 +167,19 @@ impl AuthBuilder {
             .map(|v| v.split_ascii_whitespace().map(|s| s.to_string()).collect())
             .unwrap_or_default();
 
+        let role = token_data
+            .claims
+            .roles
+            .iter()
+            .filter_map(|role| Role::from_claim(role))
+            .max();
+
         Ok(Auth {
             sub: token_data.claims.sub.into(),
             scopes,
+            token_role: role,
+            role: OnceCell::new(),
+            credential: Credential::Jwt,
         })
     }
 }
@@ -78,16 +187,71 @@ impl AuthBuilder {
 pub struct Auth {
     pub sub: EcoString,
     scopes: HashSet<String>,
+    /// The role granted by the `roles` claim of the token.
+    token_role: Option<Role>,
+    /// The effective role, resolved on the first use.
+    role: OnceCell<Role>,
+    credential: Credential,
 }
 
 impl Auth {
+    pub fn new(sub: impl Into<EcoString>, scopes: impl IntoIterator<Item = Scope>) -> Self {
+        Self {
+            sub: sub.into(),
+            scopes: scopes.into_iter().map(|s| s.as_str().to_string()).collect(),
+            token_role: None,
+            role: OnceCell::new(),
+            credential: Credential::Jwt,
+        }
+    }
+
+    /// Mark the request as authenticated with `credential`.
+    pub fn with_credential(mut self, credential: Credential) -> Self {
+        self.credential = credential;
+        self
+    }
+
+    pub fn credential(&self) -> Credential {
+        self.credential
+    }
+
+    /// Grant `role` as if it were in the token claims.
+    pub fn with_role(mut self, role: Role) -> Self {
+        self.token_role = Some(role);
+        self
+    }
+
+    /// Resolve the role with `stored_role` loaded from `dp_users` in advance,
+    /// so that [`Auth::role`] does not query it again.
+    pub fn with_stored_role(mut self, stored_role: Option<Role>) -> Self {
+        self.role = OnceCell::new_with(Some(self.resolve_role(stored_role)));
+        self
+    }
+
     pub fn has_scope(&self, scope: Scope) -> bool {
         self.scopes.contains(scope.as_str())
     }
+
+    /// Get the role of the user, which is the higher one of the role in the
+    /// token claims and the role stored in `dp_users`.
+    pub async fn role(&self, conn: impl db::Executor<'_>) -> Result<Role, db::Error> {
+        self.role
+            .get_or_try_init(|| async {
+                let stored_role = db::get_user_role(conn, &self.sub).await?.map(Role::from);
+                Ok(self.resolve_role(stored_role))
+            })
+            .await
+            .copied()
+    }
+
+    fn resolve_role(&self, stored_role: Option<Role>) -> Role {
+        self.token_role.max(stored_role).unwrap_or(Role::Student)
+    }
 }
 
 pub trait ContextAuthExt {
     fn require_scope(&self, scope: Scope) -> Result<(), async_graphql::Error>;
+    fn require_sub(&self) -> Result<&str, async_graphql::Error>;
     fn sub(&self) -> Option<&str>;
 }
 
@@ -96,6 +260,18 @@ impl ContextAuthExt for Context<'_> {
         self.data::<Auth>().map(|auth| auth.sub.as_str()).ok()
     }
 
+    fn require_sub(&self) -> Result<&str, async_graphql::Error> {
+        self.sub().ok_or_else(|| {
+            super::error::Error {
+                code: super::error::ErrorCode::Unauthorized,
+                title: EcoString::inline("Unauthorized"),
+                details: "You must login to access this API.".into(),
+                error: None,
+            }
+            .to_gql_error()
+        })
+    }
+
     fn require_scope(&self, scope: Scope) -> Result<(), async_graphql::Error> {
         let Ok(auth) = self.data::<Auth>() else {
             return Err(super::error::Error {
@@ -121,58 +297,35 @@ impl ContextAuthExt for Context<'_> {
     }
 }
 
-fn get_logto_endpoint(logto_domain: &str, endpoint: &str) -> reqwest::Url {
-    Url::parse(logto_domain)
-        .expect("logto_domain must be a valid URL")
-        .join(endpoint)
-        .expect("endpoint must be a valid path")
-}
-
-/// Get the JWKSet from the OIDC server.
-#[once(time = 3600, sync_writes = true, result = true)]
-async fn get_jwkset(logto_domain: &str) -> Result<JwkSet, AuthError> {
-    let jwkset = reqwest::get(get_logto_endpoint(logto_domain, "oidc/jwks"))
-        .await
-        .map_err(AuthError::GetJwtSetFailed)?;
-    Ok(jwkset.json().await.map_err(AuthError::GetJwtSetFailed)?)
-}
-
-// Get a JWK from the JWKSet.
-async fn get_jwk_decoding_key(logto_domain: &str) -> Result<DecodingKey, AuthError> {
-    let jwkset = get_jwkset(logto_domain).await?;
-
-    let decoding_key = jwkset
-        .keys
-        .into_iter()
-        .find_map(|key| DecodingKey::from_jwk(&key).ok());
-
-    decoding_key.ok_or(AuthError::MissingJwkInOidc)
-}
-
-fn get_validation_parameter(
-    logto_domain: &str,
-    resource_indicator: &str,
-    alg: Algorithm,
-) -> Validation {
-    let mut validation = Validation::new(alg);
-    validation.validate_nbf = true;
-    validation.set_issuer(&[get_logto_endpoint(logto_domain, "oidc")]);
-    validation.set_audience(&[resource_indicator]);
-
-    validation
-}
-
 #[derive(thiserror::Error, Debug)]
 pub enum AuthError {
+    #[error("discover OIDC configuration: {0}")]
+    DiscoveryFailed(reqwest::Error),
+
+    #[error("invalid issuer: {0}")]
+    InvalidIssuer(EcoString),
+
     #[error("get JWT set: {0}")]
     GetJwtSetFailed(reqwest::Error),
 
-    #[error("missing JWK in OIDC")]
-    MissingJwkInOidc,
+    #[error("no JWK matches kid {kid:?} and algorithm {alg:?}")]
+    NoMatchingJwk {
+        kid: Option<EcoString>,
+        alg: Algorithm,
+    },
+
+    #[error("algorithm {0:?} is not accepted")]
+    UnsupportedAlgorithm(Algorithm),
 
     #[error("decode JWT header: {0}")]
     DecodeJwtHeader(jsonwebtoken::errors::Error),
 
     #[error("decode JWT: {0}")]
     DecodeJwt(jsonwebtoken::errors::Error),
+
+    #[error("API token is invalid, expired or revoked")]
+    InvalidApiToken,
+
+    #[error("look up API token: {0}")]
+    LookUpApiToken(db::Error),
 }
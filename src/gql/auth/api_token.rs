@@ -0,0 +1,71 @@
+//! The personal access tokens, which let the scripts call the API without
+//! an interactive login.
+//!
+//! A token is `dpat_` followed by 32 random bytes in hex. Only its SHA-256
+//! hash is stored in `dp_api_tokens`.
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::db;
+
+use super::{Auth, AuthError, Credential, Scope};
+
+/// The prefix to tell the API tokens from the JWTs.
+pub const PREFIX: &str = "dpat_";
+
+/// Whether the bearer token looks like an API token.
+pub fn is_api_token(token: &str) -> bool {
+    token.starts_with(PREFIX)
+}
+
+/// Generate a new token, returning the plaintext and its hash.
+pub fn generate() -> (String, Vec<u8>) {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+
+    let token = format!("{PREFIX}{}", hex::encode(secret));
+    let hash = hash(&token);
+    (token, hash)
+}
+
+pub fn hash(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+/// Authenticate the request with an API token.
+///
+/// The token carries the scopes it was created with. Its role is the role
+/// stored for the user, as the token has no role claims.
+pub async fn authenticate(conn: impl db::Executor<'_>, token: &str) -> Result<Auth, AuthError> {
+    let api_token = db::use_api_token(conn, &hash(token))
+        .await
+        .map_err(AuthError::LookUpApiToken)?
+        .ok_or(AuthError::InvalidApiToken)?;
+
+    let auth = Auth::new(
+        api_token.user_id,
+        api_token.scopes.iter().filter_map(|s| Scope::parse(s)),
+    );
+    Ok(auth.with_credential(Credential::ApiToken))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let (token, token_hash) = generate();
+
+        assert!(is_api_token(&token));
+        assert_eq!(token.len(), PREFIX.len() + 64);
+        assert_eq!(token_hash, hash(&token));
+        assert_ne!(generate().0, token);
+    }
+
+    #[test]
+    fn test_jwt_is_not_api_token() {
+        assert!(!is_api_token("eyJhbGciOiJIUzI1NiJ9.e30.signature"));
+    }
+}
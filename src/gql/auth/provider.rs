@@ -0,0 +1,354 @@
+//! The identity providers which issue the tokens.
+
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ecow::EcoString;
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Header, Validation,
+    jwk::{Jwk, JwkSet},
+};
+use reqwest::Url;
+
+use super::AuthError;
+
+/// The source of the keys and the rules to verify a token.
+#[async_trait::async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Get the key to verify a token with `header`.
+    async fn decoding_key(&self, header: &Header) -> Result<DecodingKey, AuthError>;
+
+    /// Get the validation of a token signed with `alg`.
+    fn validation(&self, alg: Algorithm) -> Validation;
+}
+
+/// An OpenID Connect provider.
+pub struct OidcProvider {
+    issuer: EcoString,
+    audience: EcoString,
+    jwks: JwksCache,
+}
+
+impl OidcProvider {
+    pub fn new(issuer: EcoString, jwks_uri: Url, audience: EcoString, config: JwksConfig) -> Self {
+        Self {
+            issuer,
+            audience,
+            jwks: JwksCache::new(jwks_uri, config),
+        }
+    }
+
+    /// Discover the provider from `{issuer}/.well-known/openid-configuration`.
+    pub async fn discover(
+        issuer: &str,
+        audience: EcoString,
+        config: JwksConfig,
+    ) -> Result<Self, AuthError> {
+        #[derive(Debug, serde::Deserialize)]
+        struct Configuration {
+            issuer: String,
+            jwks_uri: String,
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            issuer.trim_end_matches('/')
+        );
+        tracing::debug!(url, "Discovering the OIDC provider");
+
+        let configuration = config
+            .http_client()
+            .map_err(AuthError::DiscoveryFailed)?
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(AuthError::DiscoveryFailed)?
+            .json::<Configuration>()
+            .await
+            .map_err(AuthError::DiscoveryFailed)?;
+
+        if configuration.issuer.trim_end_matches('/') != issuer.trim_end_matches('/') {
+            return Err(AuthError::InvalidIssuer(
+                format!("{issuer} announces {} as its issuer", configuration.issuer).into(),
+            ));
+        }
+
+        let jwks_uri = Url::parse(&configuration.jwks_uri).map_err(|e| {
+            AuthError::InvalidIssuer(format!("invalid jwks_uri of {issuer}: {e}").into())
+        })?;
+
+        Ok(Self::new(
+            configuration.issuer.into(),
+            jwks_uri,
+            audience,
+            config,
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider for OidcProvider {
+    async fn decoding_key(&self, header: &Header) -> Result<DecodingKey, AuthError> {
+        self.jwks
+            .decoding_key(header.kid.as_deref(), header.alg)
+            .await
+    }
+
+    fn validation(&self, alg: Algorithm) -> Validation {
+        let mut validation = Validation::new(alg);
+        validation.validate_nbf = true;
+        validation.set_issuer(&[self.issuer.as_str()]);
+        validation.set_audience(&[self.audience.as_str()]);
+
+        validation
+    }
+}
+
+/// A Logto tenant, whose OIDC endpoints are under `{domain}/oidc`.
+pub struct LogtoProvider {
+    oidc: OidcProvider,
+}
+
+impl LogtoProvider {
+    pub fn new(domain: &str, resource_indicator: EcoString, config: JwksConfig) -> Self {
+        let oidc = OidcProvider::new(
+            get_logto_endpoint(domain, "oidc").as_str().into(),
+            get_logto_endpoint(domain, "oidc/jwks"),
+            resource_indicator,
+            config,
+        );
+
+        Self { oidc }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider for LogtoProvider {
+    async fn decoding_key(&self, header: &Header) -> Result<DecodingKey, AuthError> {
+        self.oidc.decoding_key(header).await
+    }
+
+    fn validation(&self, alg: Algorithm) -> Validation {
+        self.oidc.validation(alg)
+    }
+}
+
+fn get_logto_endpoint(logto_domain: &str, endpoint: &str) -> reqwest::Url {
+    Url::parse(logto_domain)
+        .expect("logto_domain must be a valid URL")
+        .join(endpoint)
+        .expect("endpoint must be a valid path")
+}
+
+/// The tokens signed with a shared secret using HS256.
+///
+/// It is meant for the local development and the tests, where no identity
+/// provider is available.
+pub struct SharedSecretProvider {
+    key: DecodingKey,
+}
+
+impl SharedSecretProvider {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            key: DecodingKey::from_secret(secret),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider for SharedSecretProvider {
+    async fn decoding_key(&self, header: &Header) -> Result<DecodingKey, AuthError> {
+        if header.alg != Algorithm::HS256 {
+            return Err(AuthError::UnsupportedAlgorithm(header.alg));
+        }
+
+        Ok(self.key.clone())
+    }
+
+    fn validation(&self, _alg: Algorithm) -> Validation {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_nbf = true;
+        validation.validate_aud = false;
+
+        validation
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JwksConfig {
+    /// The time to keep the JWK set before fetching it again.
+    pub ttl: Duration,
+    /// The minimum interval between the fetches forced by an unknown `kid`.
+    pub refresh_interval: Duration,
+    /// The time to wait for establishing a connection to the OIDC server.
+    pub connect_timeout: Duration,
+    /// The deadline of each request to the OIDC server.
+    pub timeout: Duration,
+}
+
+impl Default for JwksConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(3600),
+            refresh_interval: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(3),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl JwksConfig {
+    fn http_client(&self) -> Result<reqwest::Client, reqwest::Error> {
+        reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .build()
+    }
+}
+
+/// The JWK set of the OIDC server.
+///
+/// A token signed by a key not in the set forces a refetch, so the keys
+/// rotated by the identity provider are picked up at once. The refetches are
+/// rate-limited to protect the identity provider from the forged tokens.
+///
+/// The cached set is never locked during a fetch, so a slow identity provider
+/// only delays the requests which need a new set.
+struct JwksCache {
+    url: Url,
+    config: JwksConfig,
+    http: reqwest::Client,
+    state: Mutex<JwksState>,
+    /// Held while fetching, so the concurrent misses share the same fetch.
+    fetching: tokio::sync::Mutex<()>,
+}
+
+#[derive(Default)]
+struct JwksState {
+    jwkset: Option<Arc<JwkSet>>,
+    /// When the cached set was fetched.
+    fetched_at: Option<Instant>,
+    /// When the last fetch was started, whether it succeeded or not.
+    attempted_at: Option<Instant>,
+}
+
+impl JwksCache {
+    fn new(url: Url, config: JwksConfig) -> Self {
+        Self {
+            url,
+            config,
+            http: config.http_client().expect("failed to build HTTP client"),
+            state: Mutex::new(JwksState::default()),
+            fetching: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Get the key to verify a token with `kid` and `alg` in its header.
+    async fn decoding_key(
+        &self,
+        kid: Option<&str>,
+        alg: Algorithm,
+    ) -> Result<DecodingKey, AuthError> {
+        let no_matching_jwk = || AuthError::NoMatchingJwk {
+            kid: kid.map(Into::into),
+            alg,
+        };
+
+        let state = self.cached();
+        let fresh = state
+            .fetched_at
+            .is_some_and(|fetched_at| fetched_at.elapsed() < self.config.ttl);
+        if fresh {
+            if let Some(key) = find_decoding_key(state.jwkset.as_deref(), kid, alg) {
+                return Ok(key);
+            }
+            if kid.is_none() {
+                return Err(no_matching_jwk());
+            }
+        }
+
+        // Count the failed fetches as well, so an unreachable server is not
+        // hammered by the forced refetches.
+        let throttled = state
+            .attempted_at
+            .is_some_and(|attempted_at| attempted_at.elapsed() < self.config.refresh_interval);
+        if throttled {
+            return find_decoding_key(state.jwkset.as_deref(), kid, alg)
+                .ok_or_else(no_matching_jwk);
+        }
+        if fresh {
+            tracing::info!(?kid, "Unknown kid. Refetching the JWK set.");
+        }
+
+        let jwkset = self.fetch(state.attempted_at).await?;
+        find_decoding_key(jwkset.as_deref(), kid, alg).ok_or_else(no_matching_jwk)
+    }
+
+    fn cached(&self) -> JwksState {
+        let state = self.state.lock().expect("JWKS cache poisoned");
+        JwksState {
+            jwkset: state.jwkset.clone(),
+            ..*state
+        }
+    }
+
+    /// Fetch the set, unless another request has tried since `seen_at`.
+    async fn fetch(&self, seen_at: Option<Instant>) -> Result<Option<Arc<JwkSet>>, AuthError> {
+        let _fetching = self.fetching.lock().await;
+
+        let state = self.cached();
+        if state.attempted_at != seen_at {
+            return Ok(state.jwkset);
+        }
+
+        tracing::debug!(url = %self.url, "Fetching the JWK set");
+        self.state.lock().expect("JWKS cache poisoned").attempted_at = Some(Instant::now());
+
+        let jwkset = self
+            .http
+            .get(self.url.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(AuthError::GetJwtSetFailed)?
+            .json::<JwkSet>()
+            .await
+            .map_err(AuthError::GetJwtSetFailed)?;
+
+        let jwkset = Arc::new(jwkset);
+        let mut state = self.state.lock().expect("JWKS cache poisoned");
+        state.jwkset = Some(jwkset.clone());
+        state.fetched_at = Some(Instant::now());
+
+        Ok(Some(jwkset))
+    }
+}
+
+/// Find the key with `kid` which can verify `alg`.
+///
+/// If the token has no `kid`, the first key for `alg` is taken.
+fn find_decoding_key(
+    jwkset: Option<&JwkSet>,
+    kid: Option<&str>,
+    alg: Algorithm,
+) -> Option<DecodingKey> {
+    jwkset?
+        .keys
+        .iter()
+        .filter(|key| kid.is_none_or(|kid| key.common.key_id.as_deref() == Some(kid)))
+        .filter(|key| is_key_for_algorithm(key, alg))
+        .find_map(|key| DecodingKey::from_jwk(key).ok())
+}
+
+/// Whether the key is declared for `alg`. A key without `alg` may be used
+/// with any algorithm of its type.
+fn is_key_for_algorithm(key: &Jwk, alg: Algorithm) -> bool {
+    key.common
+        .key_algorithm
+        .is_none_or(|key_alg| Algorithm::from_str(&key_alg.to_string()).ok() == Some(alg))
+}
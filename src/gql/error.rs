@@ -10,10 +10,14 @@ use crate::db;
 
 pub enum ErrorCode {
     NotFound,
+    AlreadyExists,
+    InUse,
     InternalError,
     Unauthorized,
     InvalidJwtToken, // poem
     InvalidQuery,    // sql_executor
+    RateLimited,     // sql_executor
+    UserDeleted,     // poem
 }
 
 pub struct Error {
@@ -27,10 +31,14 @@ impl Display for ErrorCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ErrorCode::NotFound => write!(f, "NOT_FOUND"),
+            ErrorCode::AlreadyExists => write!(f, "ALREADY_EXISTS"),
+            ErrorCode::InUse => write!(f, "IN_USE"),
             ErrorCode::InternalError => write!(f, "INTERNAL_ERROR"),
             ErrorCode::Unauthorized => write!(f, "UNAUTHORIZED"),
             ErrorCode::InvalidJwtToken => write!(f, "INVALID_JWT_TOKEN"),
             ErrorCode::InvalidQuery => write!(f, "INVALID_QUERY"),
+            ErrorCode::RateLimited => write!(f, "RATE_LIMITED"),
+            ErrorCode::UserDeleted => write!(f, "USER_DELETED"),
         }
     }
 }
@@ -60,6 +68,24 @@ impl From<db::Error> for Error {
                 details: Cow::Owned(format!("{entity} with id {id} not found")),
                 error: Some(Box::new(value)),
             },
+            db::Error::AlreadyExists { entity, ref id } => Self {
+                code: ErrorCode::AlreadyExists,
+                title: EcoString::inline("Resource exists"),
+                details: Cow::Owned(format!("{entity} with id {id} already exists")),
+                error: Some(Box::new(value)),
+            },
+            db::Error::InUse { entity, ref id } => Self {
+                code: ErrorCode::InUse,
+                title: EcoString::inline("Resource in use"),
+                details: Cow::Owned(format!("{entity} with id {id} is still in use")),
+                error: Some(Box::new(value)),
+            },
+            db::Error::UserDeleted => Self {
+                code: ErrorCode::UserDeleted,
+                title: EcoString::inline("User deleted"),
+                details: Cow::Borrowed("This account has been banned or deleted."),
+                error: Some(Box::new(value)),
+            },
             e => Self {
                 code: ErrorCode::InternalError,
                 title: EcoString::inline("Internal error"),
@@ -70,6 +96,17 @@ impl From<db::Error> for Error {
     }
 }
 
+impl From<crate::logto::Error> for Error {
+    fn from(value: crate::logto::Error) -> Self {
+        Self {
+            code: ErrorCode::InternalError,
+            title: EcoString::inline("Internal error"),
+            details: Cow::Borrowed("Failed to remove the user from the authentication service"),
+            error: Some(Box::new(value)),
+        }
+    }
+}
+
 impl Error {
     pub fn to_gql_error(&self) -> async_graphql::Error {
         async_graphql::Error::new(format!("{}: {}", self.title, self.details)).extend_with(
@@ -0,0 +1,253 @@
+use async_graphql::{
+    ComplexObject, Context, Enum, InputObject, Object, Result, SimpleObject,
+    connection::{self, Connection, Edge, OpaqueCursor},
+    dataloader::DataLoader,
+};
+use chrono::{DateTime, Utc};
+
+use crate::db;
+
+use super::{
+    error,
+    loader::{self, QuestionLoader},
+    questions::Question,
+};
+
+/// The opaque cursor of an attempt event, which wraps the event ID.
+pub type AttemptCursor = OpaqueCursor<i64>;
+
+/// The opaque cursor of a solution event, which wraps the event ID.
+pub type SolutionCursor = OpaqueCursor<i64>;
+
+#[derive(Debug, SimpleObject)]
+#[graphql(complex)]
+pub struct SolutionEvent {
+    pub id: i64,
+    pub question_id: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<db::SolutionEvent> for SolutionEvent {
+    fn from(event: db::SolutionEvent) -> Self {
+        Self {
+            id: event.solution_event_id,
+            question_id: event.question_id,
+            created_at: event.created_at,
+        }
+    }
+}
+
+#[ComplexObject]
+impl SolutionEvent {
+    async fn question<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Question> {
+        tracing::debug!("Running GraphQL query 'solution_event.question'");
+        let loader = ctx.data::<DataLoader<QuestionLoader>>()?;
+
+        loader::load_one(loader, "question", self.question_id)
+            .await
+            .map(Into::into)
+    }
+}
+
+pub struct SolutionConnectionFields {
+    user_id: String,
+}
+
+#[Object]
+impl SolutionConnectionFields {
+    /// The number of viewed solutions in all pages.
+    async fn total_count<'ctx>(&self, ctx: &Context<'ctx>) -> Result<i64> {
+        tracing::debug!("Running GraphQL query 'viewed_solutions.total_count'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::count_solution_events(pool, &self.user_id)
+            .await
+            .map_err(error::gqlize)
+    }
+}
+
+/// Resolve a page of the solution events of `user_id` as a relay connection,
+/// from the newest to the oldest.
+pub async fn list_solutions_connection(
+    pool: &db::Pool,
+    user_id: String,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> Result<Connection<SolutionCursor, SolutionEvent, SolutionConnectionFields>> {
+    connection::query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<SolutionCursor>, before: Option<SolutionCursor>, first, last| async move {
+            let cursor = db::KeysetCursor {
+                after: after.map(|cursor| cursor.0),
+                before: before.map(|cursor| cursor.0),
+                first: first.map(|n| n as i64),
+                last: last.map(|n| n as i64),
+            };
+            let page = db::list_solution_events(pool, &user_id, cursor)
+                .await
+                .map_err(error::gqlize)?;
+
+            let mut connection = Connection::with_additional_fields(
+                page.has_previous_page,
+                page.has_next_page,
+                SolutionConnectionFields { user_id },
+            );
+            connection.edges.extend(
+                page.items
+                    .into_iter()
+                    .map(|event| Edge::new(OpaqueCursor(event.solution_event_id), event.into())),
+            );
+
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+}
+
+/// A query the user has executed against a question.
+#[derive(Debug, SimpleObject)]
+#[graphql(complex)]
+pub struct AttemptEvent {
+    pub id: i64,
+    pub question_id: i64,
+    /// The SQL the user has executed.
+    pub query: String,
+    pub status: AttemptStatus,
+    /// The error message from dbrunner if the query failed to run.
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<db::AttemptEvent> for AttemptEvent {
+    fn from(event: db::AttemptEvent) -> Self {
+        Self {
+            id: event.attempt_event_id,
+            question_id: event.question_id,
+            query: event.query,
+            status: event.status.into(),
+            error: event.error,
+            created_at: event.created_at,
+        }
+    }
+}
+
+#[ComplexObject]
+impl AttemptEvent {
+    async fn question<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Question> {
+        tracing::debug!("Running GraphQL query 'attempt_event.question'");
+        let loader = ctx.data::<DataLoader<QuestionLoader>>()?;
+
+        loader::load_one(loader, "question", self.question_id)
+            .await
+            .map(Into::into)
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptStatus {
+    Pending,
+    Passed,
+    Failed,
+}
+
+impl From<db::AttemptStatus> for AttemptStatus {
+    fn from(status: db::AttemptStatus) -> Self {
+        match status {
+            db::AttemptStatus::Pending => Self::Pending,
+            db::AttemptStatus::Passed => Self::Passed,
+            db::AttemptStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<AttemptStatus> for db::AttemptStatus {
+    fn from(status: AttemptStatus) -> Self {
+        match status {
+            AttemptStatus::Pending => Self::Pending,
+            AttemptStatus::Passed => Self::Passed,
+            AttemptStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+#[derive(InputObject, Default)]
+pub struct AttemptFilter {
+    pub question_id: Option<i64>,
+    pub status: Option<AttemptStatus>,
+}
+
+impl From<AttemptFilter> for db::AttemptFilter {
+    fn from(filter: AttemptFilter) -> Self {
+        Self {
+            question_id: filter.question_id,
+            status: filter.status.map(Into::into),
+        }
+    }
+}
+
+pub struct AttemptConnectionFields {
+    user_id: String,
+    filter: db::AttemptFilter,
+}
+
+#[Object]
+impl AttemptConnectionFields {
+    /// The number of attempts in all pages.
+    async fn total_count<'ctx>(&self, ctx: &Context<'ctx>) -> Result<i64> {
+        tracing::debug!("Running GraphQL query 'attempts.total_count'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::count_attempt_events(pool, &self.user_id, self.filter)
+            .await
+            .map_err(error::gqlize)
+    }
+}
+
+/// Resolve a page of the attempts of `user_id` matching `filter` as a relay
+/// connection, from the newest to the oldest.
+pub async fn list_attempts_connection(
+    pool: &db::Pool,
+    user_id: String,
+    filter: db::AttemptFilter,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> Result<Connection<AttemptCursor, AttemptEvent, AttemptConnectionFields>> {
+    connection::query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<AttemptCursor>, before: Option<AttemptCursor>, first, last| async move {
+            let cursor = db::KeysetCursor {
+                after: after.map(|cursor| cursor.0),
+                before: before.map(|cursor| cursor.0),
+                first: first.map(|n| n as i64),
+                last: last.map(|n| n as i64),
+            };
+            let page = db::list_attempt_events(pool, &user_id, filter, cursor)
+                .await
+                .map_err(error::gqlize)?;
+
+            let mut connection = Connection::with_additional_fields(
+                page.has_previous_page,
+                page.has_next_page,
+                AttemptConnectionFields { user_id, filter },
+            );
+            connection.edges.extend(
+                page.items
+                    .into_iter()
+                    .map(|event| Edge::new(OpaqueCursor(event.attempt_event_id), event.into())),
+            );
+
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+}
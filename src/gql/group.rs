@@ -0,0 +1,399 @@
+use async_graphql::{
+    ComplexObject, Context, Enum, Object, Result, SimpleObject,
+    connection::{self, Connection, Edge, OpaqueCursor},
+};
+use chrono::{DateTime, Datelike, Duration, Utc};
+
+use crate::{
+    db,
+    gql::{
+        auth::{Auth, Role, Scope},
+        guard::{EnrollGuard, GroupGuard, MemberGuard, RoleGuard, ScopeGuard},
+    },
+};
+
+use super::{error, user::User};
+
+/// The opaque cursor of a group, which wraps the group ID.
+pub type GroupCursor = OpaqueCursor<i64>;
+
+/// The opaque cursor of a group member, which wraps the user ID.
+pub type MemberCursor = OpaqueCursor<String>;
+
+#[derive(Default)]
+pub struct GroupQuery;
+
+#[Object]
+impl GroupQuery {
+    /// List the groups. Teachers only see the groups they manage.
+    #[graphql(guard = "ScopeGuard(Scope::ManageUser).and(RoleGuard(Role::Teacher))")]
+    async fn groups<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<GroupCursor, Group, GroupConnectionFields>> {
+        tracing::debug!("Running GraphQL query 'groups'");
+        let pool = ctx.data::<db::Pool>()?;
+        let owner_id = owner_filter(ctx).await?;
+
+        connection::query(
+            after,
+            before,
+            first,
+            last,
+            |after: Option<GroupCursor>, before: Option<GroupCursor>, first, last| async move {
+                let cursor = db::KeysetCursor {
+                    after: after.map(|cursor| cursor.0),
+                    before: before.map(|cursor| cursor.0),
+                    first: first.map(|n| n as i64),
+                    last: last.map(|n| n as i64),
+                };
+                let page = db::list_groups(pool, owner_id.as_deref(), cursor)
+                    .await
+                    .map_err(error::gqlize)?;
+
+                let mut connection = Connection::with_additional_fields(
+                    page.has_previous_page,
+                    page.has_next_page,
+                    GroupConnectionFields { owner_id },
+                );
+                connection.edges.extend(
+                    page.items
+                        .into_iter()
+                        .map(|group| Edge::new(OpaqueCursor(group.group_id), group.into())),
+                );
+
+                Ok::<_, async_graphql::Error>(connection)
+            },
+        )
+        .await
+    }
+}
+
+/// The owner to filter the groups by: the teacher themselves, or `None` for
+/// the admins to see all groups.
+async fn owner_filter(ctx: &Context<'_>) -> Result<Option<String>> {
+    let auth = ctx.data::<Auth>()?;
+    let pool = ctx.data::<db::Pool>()?;
+
+    let role = auth.role(pool).await.map_err(error::gqlize)?;
+    Ok((role < Role::Admin).then(|| auth.sub.to_string()))
+}
+
+pub struct GroupConnectionFields {
+    owner_id: Option<String>,
+}
+
+#[Object]
+impl GroupConnectionFields {
+    /// The number of groups in all pages.
+    async fn total_count<'ctx>(&self, ctx: &Context<'ctx>) -> Result<i64> {
+        tracing::debug!("Running GraphQL query 'groups.total_count'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::count_groups(pool, self.owner_id.as_deref())
+            .await
+            .map_err(error::gqlize)
+    }
+}
+
+#[derive(Default)]
+pub struct GroupMutation;
+
+#[Object]
+impl GroupMutation {
+    /// Create a group managed by `owner_id`, which only admins can specify.
+    /// The group created by a teacher is managed by the teacher.
+    #[graphql(guard = "ScopeGuard(Scope::ManageUser).and(RoleGuard(Role::Teacher))")]
+    async fn create_group<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        name: String,
+        description: Option<String>,
+        owner_id: Option<String>,
+    ) -> Result<Group> {
+        tracing::debug!("Running GraphQL mutation 'create_group'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        let owner_id = match owner_filter(ctx).await? {
+            Some(teacher) => {
+                if owner_id.is_some_and(|owner_id| owner_id != teacher) {
+                    return Err(error::Error {
+                        code: error::ErrorCode::Unauthorized,
+                        title: "Unauthorized".into(),
+                        details: "Only admins can create groups for other teachers.".into(),
+                        error: None,
+                    }
+                    .to_gql_error());
+                }
+                Some(teacher)
+            }
+            None => owner_id,
+        };
+        if let Some(owner_id) = &owner_id {
+            db::get_or_initialize_user(pool, owner_id)
+                .await
+                .map_err(error::gqlize)?;
+        }
+
+        let group_id = db::create_group(
+            pool,
+            db::GroupCreateParameter {
+                name: &name,
+                description: description.as_deref(),
+                owner_id: owner_id.as_deref(),
+            },
+        )
+        .await
+        .map_err(error::gqlize)?;
+
+        db::get_group(pool, group_id)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+
+    /// Rename the group or change its description.
+    #[graphql(guard = "ScopeGuard(Scope::ManageUser).and(GroupGuard::new(id))")]
+    async fn update_group<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+        name: Option<String>,
+        description: Option<String>,
+    ) -> Result<Group> {
+        tracing::debug!("Running GraphQL mutation 'update_group'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::update_group(
+            pool,
+            id,
+            db::GroupUpdateParameter {
+                name: name.as_deref(),
+                description: description.as_deref(),
+            },
+        )
+        .await
+        .map_err(error::gqlize)?;
+
+        db::get_group(pool, id)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+
+    /// Mark the group as deleted and remove all its members from it.
+    /// Returns the ID of the deleted group.
+    #[graphql(guard = "ScopeGuard(Scope::ManageUser).and(GroupGuard::new(id))")]
+    async fn delete_group<'ctx>(&self, ctx: &Context<'ctx>, id: i64) -> Result<i64> {
+        tracing::debug!("Running GraphQL mutation 'delete_group'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::delete_group(pool, id).await.map_err(error::gqlize)?;
+
+        Ok(id)
+    }
+
+    /// Move the user into the group. The user leaves their previous group.
+    ///
+    /// Teachers can only enroll the students who are not in a group or are in
+    /// another group they manage.
+    #[graphql(
+        guard = "ScopeGuard(Scope::ManageUser).and(GroupGuard::new(group_id)).and(EnrollGuard::new(&user_id))"
+    )]
+    async fn add_user_to_group<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        user_id: String,
+        group_id: i64,
+    ) -> Result<User> {
+        tracing::debug!("Running GraphQL mutation 'add_user_to_group'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::get_group(pool, group_id).await.map_err(error::gqlize)?;
+
+        db::set_user_group(pool, &user_id, Some(group_id))
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+
+    /// Remove the user from their group. Teachers can only remove the members
+    /// of the groups they manage.
+    #[graphql(guard = "ScopeGuard(Scope::ManageUser).and(MemberGuard::new(&user_id))")]
+    async fn remove_user_from_group<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        user_id: String,
+    ) -> Result<User> {
+        tracing::debug!("Running GraphQL mutation 'remove_user_from_group'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::set_user_group(pool, &user_id, None)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Group {
+    pub group_id: i64,
+    pub name: String,
+    pub description: String,
+    /// The ID of the teacher who manages the group.
+    pub owner_id: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<db::Group> for Group {
+    fn from(group: db::Group) -> Self {
+        Self {
+            group_id: group.group_id,
+            name: group.name,
+            description: group.description,
+            owner_id: group.owner_id,
+            created_at: group.created_at,
+            updated_at: group.updated_at,
+        }
+    }
+}
+
+#[ComplexObject]
+impl Group {
+    #[graphql(guard = "ScopeGuard(Scope::ManageUser).and(GroupGuard::new(self.group_id))")]
+    async fn members<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<MemberCursor, User, MemberConnectionFields>> {
+        tracing::debug!("Running GraphQL query 'group.members'");
+        let pool = ctx.data::<db::Pool>()?;
+        let group_id = self.group_id;
+
+        connection::query(
+            after,
+            before,
+            first,
+            last,
+            |after: Option<MemberCursor>, before: Option<MemberCursor>, first, last| async move {
+                let cursor = db::KeysetCursor {
+                    after: after.map(|cursor| cursor.0),
+                    before: before.map(|cursor| cursor.0),
+                    first: first.map(|n| n as i64),
+                    last: last.map(|n| n as i64),
+                };
+                let page = db::list_group_members(pool, group_id, cursor)
+                    .await
+                    .map_err(error::gqlize)?;
+
+                let mut connection = Connection::with_additional_fields(
+                    page.has_previous_page,
+                    page.has_next_page,
+                    MemberConnectionFields { group_id },
+                );
+                connection.edges.extend(
+                    page.items
+                        .into_iter()
+                        .map(|user| Edge::new(OpaqueCursor(user.user_id.clone()), user.into())),
+                );
+
+                Ok::<_, async_graphql::Error>(connection)
+            },
+        )
+        .await
+    }
+
+    /// Rank the members by the questions they have passed, weighted by
+    /// difficulty. The ties are broken by the time from the first attempt to
+    /// the first pass.
+    #[graphql(guard = "ScopeGuard(Scope::ManageUser).and(GroupGuard::new(self.group_id))")]
+    async fn leaderboard<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        #[graphql(default)] window: LeaderboardWindow,
+    ) -> Result<Vec<LeaderboardEntry>> {
+        tracing::debug!("Running GraphQL query 'group.leaderboard'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        let entries = db::get_group_leaderboard(pool, self.group_id, window.since(Utc::now()))
+            .await
+            .map_err(error::gqlize)?;
+        Ok(entries.into_iter().map(Into::into).collect())
+    }
+}
+
+#[derive(Enum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderboardWindow {
+    /// Count the attempts since Monday 00:00 (UTC) of this week.
+    Week,
+    /// Count all the attempts.
+    #[default]
+    AllTime,
+}
+
+impl LeaderboardWindow {
+    /// The start of the window relative to `now`.
+    fn since(self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Week => {
+                let monday =
+                    now.date_naive() - Duration::days(now.weekday().num_days_from_monday().into());
+                Some(monday.and_time(chrono::NaiveTime::MIN).and_utc())
+            }
+            Self::AllTime => None,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct LeaderboardEntry {
+    pub user_id: String,
+    /// The rank starting from 1. Tied members share a rank.
+    pub rank: i64,
+    /// The number of questions passed.
+    pub passed: i64,
+    /// The passed questions weighted by difficulty: 1 for easy, 2 for medium
+    /// and 3 for hard questions.
+    pub score: i64,
+    /// The total seconds from the first attempt to the first pass of each
+    /// passed question.
+    pub time_to_pass: Option<i64>,
+}
+
+impl From<db::LeaderboardEntry> for LeaderboardEntry {
+    fn from(entry: db::LeaderboardEntry) -> Self {
+        Self {
+            user_id: entry.user_id,
+            rank: entry.rank,
+            passed: entry.passed,
+            score: entry.score,
+            time_to_pass: entry.time_to_pass,
+        }
+    }
+}
+
+pub struct MemberConnectionFields {
+    group_id: i64,
+}
+
+#[Object]
+impl MemberConnectionFields {
+    /// The number of members in all pages.
+    async fn total_count<'ctx>(&self, ctx: &Context<'ctx>) -> Result<i64> {
+        tracing::debug!("Running GraphQL query 'group.members.total_count'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::count_group_members(pool, self.group_id)
+            .await
+            .map_err(error::gqlize)
+    }
+}
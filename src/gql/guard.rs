@@ -0,0 +1,227 @@
+//! The guards to authorize the access to the fields.
+//!
+//! Combine them with [`async_graphql::GuardExt`], for example
+//! `#[graphql(guard = "ScopeGuard(Scope::ManageUser).and(GroupGuard::new(id))")]`.
+
+use std::borrow::Cow;
+
+use async_graphql::{Context, Guard, Result, dataloader::DataLoader};
+use ecow::EcoString;
+
+use crate::db;
+
+use super::{
+    auth::{Auth, ContextAuthExt, Credential, Role, Scope},
+    error,
+    loader::{self, GroupLoader},
+};
+
+/// Require the token to grant the scope.
+pub struct ScopeGuard(pub Scope);
+
+impl Guard for ScopeGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.require_scope(self.0)
+    }
+}
+
+/// Reject the requests authenticated with an API token, for the actions which
+/// only the user should take, such as managing the credentials.
+pub struct InteractiveGuard;
+
+impl Guard for InteractiveGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.require_sub()?;
+
+        if ctx.data::<Auth>()?.credential() == Credential::ApiToken {
+            return Err(forbidden(
+                "This action cannot be performed with an API token.",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Require the user to have the role or a more privileged one.
+pub struct RoleGuard(pub Role);
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let role = current_role(ctx).await?.1;
+
+        if role < self.0 {
+            return Err(forbidden(format!(
+                "{} is required to perform this action",
+                self.0
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Allow the admins and the teacher who manages the group.
+pub struct GroupGuard {
+    group_id: i64,
+}
+
+impl GroupGuard {
+    pub fn new(group_id: i64) -> Self {
+        Self { group_id }
+    }
+}
+
+impl Guard for GroupGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let (auth, role) = current_role(ctx).await?;
+
+        match role {
+            Role::Admin => Ok(()),
+            Role::Teacher => {
+                let loader = ctx.data::<DataLoader<GroupLoader>>()?;
+                let group = loader::load_one(loader, "group", self.group_id).await?;
+
+                if group.owner_id.as_deref() == Some(auth.sub.as_str()) {
+                    Ok(())
+                } else {
+                    Err(forbidden("You can only manage your own groups."))
+                }
+            }
+            Role::Student => Err(forbidden("teacher is required to perform this action")),
+        }
+    }
+}
+
+/// Allow the user themselves, the admins and the teacher who manages the
+/// group of the user.
+pub struct UserGuard<'a> {
+    user_id: &'a str,
+}
+
+impl<'a> UserGuard<'a> {
+    pub fn new(user_id: &'a str) -> Self {
+        Self { user_id }
+    }
+}
+
+impl Guard for UserGuard<'_> {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let (auth, role) = current_role(ctx).await?;
+
+        if auth.sub == self.user_id || role == Role::Admin {
+            return Ok(());
+        }
+
+        if role == Role::Teacher {
+            let pool = ctx.data::<db::Pool>()?;
+            let managed = db::is_user_in_owned_group(pool, &auth.sub, self.user_id)
+                .await
+                .map_err(error::gqlize)?;
+
+            if managed {
+                return Ok(());
+            }
+        }
+
+        Err(forbidden("You can only access the data of your students."))
+    }
+}
+
+/// Allow the admins and the teacher who manages the group of the user, but not
+/// the user themselves.
+pub struct MemberGuard<'a> {
+    user_id: &'a str,
+}
+
+impl<'a> MemberGuard<'a> {
+    pub fn new(user_id: &'a str) -> Self {
+        Self { user_id }
+    }
+}
+
+impl Guard for MemberGuard<'_> {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let (auth, role) = current_role(ctx).await?;
+
+        match role {
+            Role::Admin => Ok(()),
+            Role::Teacher => {
+                let pool = ctx.data::<db::Pool>()?;
+                let managed = db::is_user_in_owned_group(pool, &auth.sub, self.user_id)
+                    .await
+                    .map_err(error::gqlize)?;
+
+                if managed {
+                    Ok(())
+                } else {
+                    Err(forbidden(
+                        "You can only manage the members of your own groups.",
+                    ))
+                }
+            }
+            Role::Student => Err(forbidden("teacher is required to perform this action")),
+        }
+    }
+}
+
+/// Allow the admins, and the teachers to move a student who is not in a group
+/// or is in one of their groups.
+pub struct EnrollGuard<'a> {
+    user_id: &'a str,
+}
+
+impl<'a> EnrollGuard<'a> {
+    pub fn new(user_id: &'a str) -> Self {
+        Self { user_id }
+    }
+}
+
+impl Guard for EnrollGuard<'_> {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let (auth, role) = current_role(ctx).await?;
+
+        match role {
+            Role::Admin => Ok(()),
+            Role::Teacher => {
+                // The effective role of the teacher themselves is known, even
+                // if it is only granted by the token claims.
+                let enrollable = auth.sub != self.user_id && {
+                    let pool = ctx.data::<db::Pool>()?;
+                    db::can_enroll_user(pool, &auth.sub, self.user_id)
+                        .await
+                        .map_err(error::gqlize)?
+                };
+
+                if enrollable {
+                    Ok(())
+                } else {
+                    Err(forbidden(
+                        "You can only enroll the students who are not in other groups.",
+                    ))
+                }
+            }
+            Role::Student => Err(forbidden("teacher is required to perform this action")),
+        }
+    }
+}
+
+async fn current_role<'ctx>(ctx: &Context<'ctx>) -> Result<(&'ctx Auth, Role)> {
+    ctx.require_sub()?;
+
+    let auth = ctx.data::<Auth>()?;
+    let pool = ctx.data::<db::Pool>()?;
+    let role = auth.role(pool).await.map_err(error::gqlize)?;
+
+    Ok((auth, role))
+}
+
+fn forbidden(details: impl Into<Cow<'static, str>>) -> async_graphql::Error {
+    error::Error {
+        code: error::ErrorCode::Unauthorized,
+        title: EcoString::inline("Unauthorized"),
+        details: details.into(),
+        error: None,
+    }
+    .to_gql_error()
+}
@@ -0,0 +1,84 @@
+//! Batch the lookups of the resources referenced by other resources, such as
+//! the schema of a question, into a single query per request.
+
+use std::{borrow::Cow, collections::HashMap, fmt::Display, hash::Hash, sync::Arc};
+
+use async_graphql::dataloader::{DataLoader, Loader};
+use ecow::{EcoString, eco_format};
+
+use crate::db;
+
+use super::error;
+
+pub struct SchemaLoader(pub db::Pool);
+pub struct GroupLoader(pub db::Pool);
+pub struct QuestionLoader(pub db::Pool);
+
+impl Loader<String> for SchemaLoader {
+    type Value = db::Schema;
+    type Error = Arc<db::Error>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let schemas = db::get_schemas(&self.0, keys).await?;
+
+        Ok(schemas
+            .into_iter()
+            .map(|schema| (schema.schema_id.clone(), schema))
+            .collect())
+    }
+}
+
+impl Loader<i64> for GroupLoader {
+    type Value = db::Group;
+    type Error = Arc<db::Error>;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let groups = db::get_groups(&self.0, keys).await?;
+
+        Ok(groups
+            .into_iter()
+            .map(|group| (group.group_id, group))
+            .collect())
+    }
+}
+
+impl Loader<i64> for QuestionLoader {
+    type Value = db::Question;
+    type Error = Arc<db::Error>;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let questions = db::get_questions(&self.0, keys).await?;
+
+        Ok(questions
+            .into_iter()
+            .map(|question| (question.question_id, question))
+            .collect())
+    }
+}
+
+/// Load the `entity` with ID `key`, which is not found if it is missing or
+/// has been deleted.
+pub async fn load_one<L, K>(
+    loader: &DataLoader<L>,
+    entity: &'static str,
+    key: K,
+) -> Result<L::Value, async_graphql::Error>
+where
+    L: Loader<K, Error = Arc<db::Error>>,
+    K: Send + Sync + Hash + Eq + Clone + Display + 'static,
+{
+    match loader.load_one(key.clone()).await {
+        Ok(Some(value)) => Ok(value),
+        Ok(None) => Err(error::gqlize(db::Error::NotFound {
+            entity,
+            id: eco_format!("{key}"),
+        })),
+        Err(e) => Err(error::Error {
+            code: error::ErrorCode::InternalError,
+            title: EcoString::inline("Internal error"),
+            details: Cow::Borrowed("An internal error occurred"),
+            error: Some(Box::new(e)),
+        }
+        .to_gql_error()),
+    }
+}
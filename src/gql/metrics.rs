@@ -0,0 +1,136 @@
+//! Record the GraphQL requests and resolvers as Prometheus metrics.
+
+use std::{sync::Arc, time::Instant};
+
+use async_graphql::{
+    Response, ServerResult, Value,
+    extensions::{
+        Extension, ExtensionContext, ExtensionFactory, NextRequest, NextResolve, ResolveInfo,
+    },
+};
+
+/// The async-graphql extension to record the metrics of each request.
+///
+/// - `graphql_requests_total{status}`: the requests, by `ok` or `error`.
+/// - `graphql_request_duration_seconds`: the time to run a request.
+/// - `graphql_errors_total{code}`: the errors, by the error code.
+/// - `graphql_resolver_duration_seconds{parent_type, field}`: the time to
+///   resolve a field, excluding the introspection.
+pub struct Metrics;
+
+impl ExtensionFactory for Metrics {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(MetricsExtension)
+    }
+}
+
+struct MetricsExtension;
+
+#[async_trait::async_trait]
+impl Extension for MetricsExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let start = Instant::now();
+        let response = next.run(ctx).await;
+        metrics::histogram!("graphql_request_duration_seconds").record(start.elapsed());
+
+        let status = if response.is_ok() { "ok" } else { "error" };
+        metrics::counter!("graphql_requests_total", "status" => status).increment(1);
+
+        for error in &response.errors {
+            let code = match error.extensions.as_ref().and_then(|e| e.get("code")) {
+                Some(Value::String(code)) => code.clone(),
+                _ => "UNKNOWN".to_string(),
+            };
+            metrics::counter!("graphql_errors_total", "code" => code).increment(1);
+        }
+
+        response
+    }
+
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        if info.is_for_introspection || info.name.starts_with("__") {
+            return next.run(ctx, info).await;
+        }
+
+        let labels = [
+            ("parent_type", info.parent_type.to_string()),
+            ("field", info.name.to_string()),
+        ];
+        let start = Instant::now();
+        let result = next.run(ctx, info).await;
+        metrics::histogram!("graphql_resolver_duration_seconds", &labels).record(start.elapsed());
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{EmptyMutation, EmptySubscription, ErrorExtensions, Object, Schema};
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    use super::Metrics;
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn value(&self) -> i32 {
+            42
+        }
+
+        async fn fail(&self) -> async_graphql::Result<i32> {
+            Err(async_graphql::Error::new("failed").extend_with(|_, e| e.set("code", "NOT_FOUND")))
+        }
+    }
+
+    fn run(queries: &[&str]) -> String {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+            .extension(Metrics)
+            .finish();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        metrics::with_local_recorder(&recorder, || {
+            runtime.block_on(async {
+                for query in queries {
+                    schema.execute(*query).await;
+                }
+            })
+        });
+
+        handle.render()
+    }
+
+    #[test]
+    fn test_request_metrics() {
+        let output = run(&["{ value }", "{ value }", "{ fail }"]);
+
+        assert!(output.contains(r#"graphql_requests_total{status="ok"} 2"#));
+        assert!(output.contains(r#"graphql_requests_total{status="error"} 1"#));
+        assert!(output.contains(r#"graphql_errors_total{code="NOT_FOUND"} 1"#));
+        assert!(output.contains("graphql_request_duration_seconds_count 3"));
+    }
+
+    #[test]
+    fn test_resolver_metrics_skip_introspection() {
+        let output = run(&[
+            "{ value __typename }",
+            "{ __schema { queryType { name } } }",
+        ]);
+
+        assert!(output.contains(
+            r#"graphql_resolver_duration_seconds_count{parent_type="Query",field="value"} 1"#
+        ));
+        assert!(!output.contains("__schema"));
+        assert!(!output.contains("__typename"));
+    }
+}
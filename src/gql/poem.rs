@@ -1,39 +1,38 @@
-use super::auth::AuthBuilder;
+use super::Schema;
+use super::auth::{Auth, AuthBuilder, AuthError, api_token};
 use super::error::{Error, ErrorCode};
-use super::{Mutation, Query};
-use async_graphql::{EmptySubscription, Pos, Response, Schema};
-use async_graphql_poem::{GraphQLRequest, GraphQLResponse};
+use super::user_cache::UserCache;
+use crate::db;
+use async_graphql::http::ALL_WEBSOCKET_PROTOCOLS;
+use async_graphql::{Data, Pos, Response};
+use async_graphql_poem::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
 use ecow::EcoString;
-use poem::handler;
 use poem::http::HeaderMap;
-use poem::web::Data;
+use poem::web::Data as PoemData;
+use poem::web::websocket::WebSocket;
+use poem::{IntoResponse, handler};
 
 #[handler]
 pub async fn index(
-    schema: Data<&Schema<Query, Mutation, EmptySubscription>>,
-    auth_builder: Data<&AuthBuilder>,
+    schema: PoemData<&Schema>,
+    auth_builder: PoemData<&AuthBuilder>,
+    pool: PoemData<&db::Pool>,
+    user_cache: PoemData<&UserCache>,
     headers: &HeaderMap,
     req: GraphQLRequest,
 ) -> GraphQLResponse {
     let mut req = req.0;
 
-    if let Some(token) = extract_jwt(headers) {
-        let auth = auth_builder.build(&token).await.map_err(|e| Error {
-            code: ErrorCode::InvalidJwtToken,
-            title: EcoString::inline("Invalid token"),
-            details: e.to_string().into(),
-            error: Some(Box::new(e)),
-        });
-
-        match auth {
+    if let Some(token) = extract_bearer_token(headers) {
+        match authenticate(&auth_builder, &pool, &user_cache, &token).await {
             Ok(auth) => {
                 req = req.data(auth);
             }
             Err(e) => {
                 // fixme: a bit ugly 🤔
-                return GraphQLResponse(Response::from_errors(vec![e
-                    .to_gql_error()
-                    .into_server_error(Pos::default())]));
+                return GraphQLResponse(Response::from_errors(vec![
+                    e.to_gql_error().into_server_error(Pos::default()),
+                ]));
             }
         }
     }
@@ -41,7 +40,103 @@ pub async fn index(
     schema.execute(req).await.into()
 }
 
-fn extract_jwt(headers: &HeaderMap) -> Option<String> {
+/// Serve the GraphQL subscriptions over WebSocket.
+///
+/// The credential can be passed in the `Authorization` header of the upgrade
+/// request, or in the `Authorization` field of the `connection_init` payload
+/// since browsers cannot set headers on WebSocket requests.
+#[handler]
+pub async fn subscription(
+    schema: PoemData<&Schema>,
+    auth_builder: PoemData<&AuthBuilder>,
+    pool: PoemData<&db::Pool>,
+    user_cache: PoemData<&UserCache>,
+    headers: &HeaderMap,
+    protocol: GraphQLProtocol,
+    websocket: WebSocket,
+) -> impl IntoResponse {
+    let schema = schema.0.clone();
+    let auth_builder = auth_builder.0.clone();
+    let pool = pool.0.clone();
+    let user_cache = user_cache.0.clone();
+    let header_token = extract_bearer_token(headers);
+
+    websocket
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            GraphQLWebSocket::new(stream, schema, protocol)
+                .on_connection_init(move |payload| async move {
+                    let payload_token = payload
+                        .get("Authorization")
+                        .and_then(|value| value.as_str())
+                        .and_then(|value| value.strip_prefix("Bearer "))
+                        .map(|token| token.to_string());
+
+                    let mut data = Data::default();
+                    if let Some(token) = payload_token.or(header_token) {
+                        let auth = authenticate(&auth_builder, &pool, &user_cache, &token)
+                            .await
+                            .map_err(|e| e.to_gql_error())?;
+                        data.insert(auth);
+                    }
+
+                    Ok(data)
+                })
+                .serve()
+        })
+}
+
+/// Authenticate with an API token or a JWT, and reject the banned or deleted
+/// users.
+async fn authenticate(
+    auth_builder: &AuthBuilder,
+    pool: &db::Pool,
+    user_cache: &UserCache,
+    token: &str,
+) -> Result<Auth, Error> {
+    let auth = if api_token::is_api_token(token) {
+        api_token::authenticate(pool, token).await
+    } else {
+        auth_builder.build(token).await
+    };
+    let auth = auth.map_err(auth_error)?;
+
+    let user = user_cache.get(pool, &auth.sub).await?;
+    if user.as_ref().is_some_and(|user| user.deleted_at.is_some()) {
+        return Err(db::Error::UserDeleted.into());
+    }
+
+    // The role granted by the token claims only applies to this request, so
+    // that revoking it at the identity provider takes effect.
+    Ok(auth.with_stored_role(user.map(|user| user.role.into())))
+}
+
+/// Report the failures of the identity provider or the database as internal
+/// errors, without exposing their details to the client.
+fn auth_error(e: AuthError) -> Error {
+    match e {
+        AuthError::DiscoveryFailed(_)
+        | AuthError::InvalidIssuer(_)
+        | AuthError::GetJwtSetFailed(_)
+        | AuthError::LookUpApiToken(_) => {
+            tracing::error!(error = %e, "Failed to authenticate the request");
+            Error {
+                code: ErrorCode::InternalError,
+                title: EcoString::inline("Internal error"),
+                details: "Unable to verify the token.".into(),
+                error: Some(Box::new(e)),
+            }
+        }
+        e => Error {
+            code: ErrorCode::InvalidJwtToken,
+            title: EcoString::inline("Invalid token"),
+            details: e.to_string().into(),
+            error: Some(Box::new(e)),
+        },
+    }
+}
+
+fn extract_bearer_token(headers: &HeaderMap) -> Option<String> {
     headers
         .get("Authorization")
         .and_then(|header| header.to_str().ok())
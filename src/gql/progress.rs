@@ -0,0 +1,131 @@
+use async_graphql::{ComplexObject, Context, Result, SimpleObject};
+use chrono::{DateTime, Utc};
+
+use crate::db;
+
+use super::{error, questions::Difficulty};
+
+/// The progress of a user on the questions which have not been deleted.
+///
+/// Every question falls into exactly one of `solved`, `attempted` and
+/// `untouched`: a question is only counted as attempted if none of its
+/// attempts has passed.
+#[derive(Debug, SimpleObject)]
+#[graphql(complex)]
+pub struct Progress {
+    #[graphql(visible = false)]
+    pub user_id: String,
+    pub total: ProgressCount,
+    /// The progress grouped by difficulty, from the easiest to the hardest.
+    pub by_difficulty: Vec<DifficultyProgress>,
+    /// The progress grouped by question type, in alphabetical order.
+    pub by_type: Vec<TypeProgress>,
+}
+
+#[derive(Debug, Default, Clone, Copy, SimpleObject)]
+pub struct ProgressCount {
+    pub solved: i64,
+    pub attempted: i64,
+    pub untouched: i64,
+}
+
+impl ProgressCount {
+    fn add(&mut self, breakdown: &db::ProgressBreakdown) {
+        self.solved += breakdown.solved;
+        self.attempted += breakdown.attempted;
+        self.untouched += breakdown.untouched;
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct DifficultyProgress {
+    pub difficulty: Difficulty,
+    #[graphql(flatten)]
+    pub count: ProgressCount,
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct TypeProgress {
+    #[graphql(name = "type")]
+    pub question_type: String,
+    #[graphql(flatten)]
+    pub count: ProgressCount,
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct FirstSolve {
+    pub question_id: i64,
+    pub solved_at: DateTime<Utc>,
+}
+
+impl From<db::FirstSolve> for FirstSolve {
+    fn from(solve: db::FirstSolve) -> Self {
+        Self {
+            question_id: solve.question_id,
+            solved_at: solve.solved_at,
+        }
+    }
+}
+
+impl Progress {
+    /// Sum the breakdown by difficulty and type up into the progress of
+    /// `user_id`.
+    pub fn new(user_id: String, breakdown: Vec<db::ProgressBreakdown>) -> Self {
+        let mut total = ProgressCount::default();
+        let mut by_difficulty = Vec::<DifficultyProgress>::new();
+        let mut by_type = Vec::<TypeProgress>::new();
+
+        // The breakdown is sorted by difficulty, and then by type.
+        for row in &breakdown {
+            total.add(row);
+
+            let difficulty = row.difficulty.into();
+            match by_difficulty.last_mut() {
+                Some(last) if last.difficulty == difficulty => last.count.add(row),
+                _ => {
+                    let mut count = ProgressCount::default();
+                    count.add(row);
+                    by_difficulty.push(DifficultyProgress { difficulty, count });
+                }
+            }
+
+            match by_type
+                .iter_mut()
+                .find(|progress| progress.question_type == row.question_type)
+            {
+                Some(progress) => progress.count.add(row),
+                None => {
+                    let mut count = ProgressCount::default();
+                    count.add(row);
+                    by_type.push(TypeProgress {
+                        question_type: row.question_type.clone(),
+                        count,
+                    });
+                }
+            }
+        }
+        by_type.sort_by(|a, b| a.question_type.cmp(&b.question_type));
+
+        Self {
+            user_id,
+            total,
+            by_difficulty,
+            by_type,
+        }
+    }
+}
+
+#[ComplexObject]
+impl Progress {
+    /// The time the user first passed each question, from the earliest to
+    /// the latest.
+    async fn first_solves<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<FirstSolve>> {
+        tracing::debug!("Running GraphQL query 'progress.first_solves'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        let solves = db::list_first_solves(pool, &self.user_id)
+            .await
+            .map_err(error::gqlize)?;
+        Ok(solves.into_iter().map(Into::into).collect())
+    }
+}
@@ -1,13 +1,28 @@
-use async_graphql::{ComplexObject, Context, Enum, Object, Result, SimpleObject};
+use async_graphql::{
+    ComplexObject, Context, Enum, InputObject, MaybeUndefined, Object, Result, SimpleObject,
+    connection::{self, Connection, Edge, OpaqueCursor},
+    dataloader::DataLoader,
+};
 use chrono::{DateTime, Utc};
 use sqlx::{Pool, Postgres};
 
 use crate::{
     db,
-    gql::auth::{ContextAuthExt, Scope},
+    gql::{
+        auth::{ContextAuthExt, Role, Scope},
+        guard::{RoleGuard, ScopeGuard},
+    },
+};
+
+use super::{
+    error,
+    event::{self, AttemptConnectionFields, AttemptCursor, AttemptEvent, AttemptStatus},
+    loader::{self, SchemaLoader},
+    schema::Schema,
 };
 
-use super::schema::Schema;
+/// The opaque cursor of a question, which wraps the question ID.
+pub type QuestionCursor = OpaqueCursor<i64>;
 
 #[derive(Default)]
 pub struct QuestionQuery;
@@ -16,29 +31,26 @@ impl QuestionQuery {}
 
 #[Object]
 impl QuestionQuery {
-    // FIXME: better questions query (relay connection)
-
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(guard = "ScopeGuard(Scope::ReadPublicResource)")]
     async fn questions<'ctx>(
         &self,
         ctx: &Context<'ctx>,
-        limit: Option<i64>,
-        offset: Option<i64>,
-    ) -> Result<Vec<Question>> {
-        ctx.require_scope(Scope::ReadPublicResource)?;
-
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+        #[graphql(default)] filter: QuestionFilter,
+        #[graphql(default)] order: QuestionOrder,
+    ) -> Result<Connection<QuestionCursor, Question, QuestionConnectionFields>> {
         tracing::debug!("Running GraphQL query 'questions'");
         let pool = ctx.data::<db::Pool>()?;
-        let cursor = db::Cursor { limit, offset };
 
-        db::list_questions(pool, cursor)
-            .await
-            .map(|questions| questions.into_iter().map(Into::into).collect())
-            .map_err(Into::into)
+        list_questions_connection(pool, filter.into(), order, after, before, first, last).await
     }
 
+    #[graphql(guard = "ScopeGuard(Scope::ReadPublicResource)")]
     async fn question<'ctx>(&self, ctx: &Context<'ctx>, id: i64) -> Result<Question> {
-        ctx.require_scope(Scope::ReadPublicResource)?;
-
         tracing::debug!("Running GraphQL query 'question'");
         let pool = ctx.data::<Pool<Postgres>>()?;
 
@@ -49,6 +61,230 @@ impl QuestionQuery {
     }
 }
 
+#[derive(InputObject, Default)]
+pub struct QuestionFilter {
+    pub difficulty: Option<Difficulty>,
+    #[graphql(name = "type")]
+    pub question_type: Option<String>,
+    pub schema_id: Option<String>,
+    /// Match the questions whose title or description contains this text.
+    pub search: Option<String>,
+}
+
+impl From<QuestionFilter> for db::QuestionFilter {
+    fn from(filter: QuestionFilter) -> Self {
+        Self {
+            difficulty: filter.difficulty.map(Into::into),
+            question_type: filter.question_type,
+            schema_id: filter.schema_id,
+            search: filter.search,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionOrder {
+    /// Order by the question ID.
+    #[default]
+    Id,
+    /// Order by the creation time, from the newest to the oldest.
+    Newest,
+    /// Order by the difficulty, from the easiest to the hardest.
+    Difficulty,
+    /// Order by the title alphabetically.
+    Title,
+}
+
+impl From<QuestionOrder> for db::QuestionOrder {
+    fn from(order: QuestionOrder) -> Self {
+        match order {
+            QuestionOrder::Id => Self::Id,
+            QuestionOrder::Newest => Self::Newest,
+            QuestionOrder::Difficulty => Self::Difficulty,
+            QuestionOrder::Title => Self::Title,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct QuestionMutation;
+
+#[Object]
+impl QuestionMutation {
+    #[graphql(guard = "ScopeGuard(Scope::WriteResource).and(RoleGuard(Role::Admin))")]
+    async fn create_question<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        input: CreateQuestionInput,
+    ) -> Result<Question> {
+        tracing::debug!("Running GraphQL mutation 'create_question'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        let question_id = db::create_question(
+            pool,
+            db::QuestionCreateParameter {
+                schema_id: input.schema_id.as_deref(),
+                question_type: &input.question_type,
+                difficulty: input.difficulty.into(),
+                title: &input.title,
+                description: input.description.as_deref(),
+                answer: &input.answer,
+                solution_video: input.solution_video.as_deref(),
+            },
+        )
+        .await
+        .map_err(error::gqlize)?;
+
+        db::get_question(pool, question_id)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+
+    #[graphql(guard = "ScopeGuard(Scope::WriteResource).and(RoleGuard(Role::Admin))")]
+    async fn update_question<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+        input: UpdateQuestionInput,
+    ) -> Result<Question> {
+        tracing::debug!("Running GraphQL mutation 'update_question'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::update_question(
+            pool,
+            id,
+            db::QuestionUpdateParameter {
+                schema_id: input.schema_id.as_opt_deref(),
+                question_type: input.question_type.as_deref(),
+                difficulty: input.difficulty.map(Into::into),
+                title: input.title.as_deref(),
+                description: input.description.as_deref(),
+                answer: input.answer.as_deref(),
+                solution_video: input.solution_video.as_opt_deref(),
+            },
+        )
+        .await
+        .map_err(error::gqlize)?;
+
+        db::get_question(pool, id)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+
+    /// Mark the question as deleted. Returns the ID of the deleted question.
+    #[graphql(guard = "ScopeGuard(Scope::WriteResource).and(RoleGuard(Role::Admin))")]
+    async fn delete_question<'ctx>(&self, ctx: &Context<'ctx>, id: i64) -> Result<i64> {
+        tracing::debug!("Running GraphQL mutation 'delete_question'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::delete_question(pool, id).await.map_err(error::gqlize)?;
+
+        Ok(id)
+    }
+
+    #[graphql(guard = "ScopeGuard(Scope::WriteResource).and(RoleGuard(Role::Admin))")]
+    async fn restore_question<'ctx>(&self, ctx: &Context<'ctx>, id: i64) -> Result<Question> {
+        tracing::debug!("Running GraphQL mutation 'restore_question'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::restore_question(pool, id)
+            .await
+            .map_err(error::gqlize)?;
+
+        db::get_question(pool, id)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+}
+
+#[derive(InputObject)]
+pub struct CreateQuestionInput {
+    pub schema_id: Option<String>,
+    #[graphql(name = "type")]
+    pub question_type: String,
+    pub difficulty: Difficulty,
+    pub title: String,
+    pub description: Option<String>,
+    pub answer: String,
+    pub solution_video: Option<String>,
+}
+
+/// The fields to update. The omitted fields are left unchanged.
+#[derive(InputObject)]
+pub struct UpdateQuestionInput {
+    /// Set to `null` to detach the question from its schema.
+    pub schema_id: MaybeUndefined<String>,
+    #[graphql(name = "type")]
+    pub question_type: Option<String>,
+    pub difficulty: Option<Difficulty>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub answer: Option<String>,
+    /// Set to `null` to remove the solution video.
+    pub solution_video: MaybeUndefined<String>,
+}
+
+pub struct QuestionConnectionFields {
+    filter: db::QuestionFilter,
+}
+
+#[Object]
+impl QuestionConnectionFields {
+    /// The number of questions in all pages.
+    async fn total_count<'ctx>(&self, ctx: &Context<'ctx>) -> Result<i64> {
+        tracing::debug!("Running GraphQL query 'questions.total_count'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::count_questions(pool, &self.filter)
+            .await
+            .map_err(Into::into)
+    }
+}
+
+/// Resolve a page of the questions matching `filter` as a relay connection.
+pub async fn list_questions_connection(
+    pool: &db::Pool,
+    filter: db::QuestionFilter,
+    order: QuestionOrder,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> Result<Connection<QuestionCursor, Question, QuestionConnectionFields>> {
+    connection::query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<QuestionCursor>, before: Option<QuestionCursor>, first, last| async move {
+            let cursor = db::KeysetCursor {
+                after: after.map(|cursor| cursor.0),
+                before: before.map(|cursor| cursor.0),
+                first: first.map(|n| n as i64),
+                last: last.map(|n| n as i64),
+            };
+            let page = db::list_questions(pool, &filter, order.into(), cursor).await?;
+
+            let mut connection = Connection::with_additional_fields(
+                page.has_previous_page,
+                page.has_next_page,
+                QuestionConnectionFields { filter },
+            );
+            connection.edges.extend(
+                page.items
+                    .into_iter()
+                    .map(|question| Edge::new(OpaqueCursor(question.question_id), question.into())),
+            );
+
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+}
+
 #[derive(Debug, SimpleObject)]
 #[graphql(complex)]
 pub struct Question {
@@ -64,30 +300,59 @@ pub struct Question {
 
 #[ComplexObject]
 impl Question {
+    #[graphql(guard = "ScopeGuard(Scope::ReadPublicResource)")]
     async fn schema<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<Schema>> {
-        ctx.require_scope(Scope::ReadPublicResource)?;
-
         tracing::debug!("Running GraphQL query 'question.schema'");
-        let pool = ctx.data::<Pool<Postgres>>()?;
+        let loader = ctx.data::<DataLoader<SchemaLoader>>()?;
 
         match self.schema_id {
-            Some(ref schema_id) => db::get_schema(pool, schema_id)
+            Some(ref schema_id) => loader::load_one(loader, "schema", schema_id.clone())
                 .await
-                .map(|schema| Some(schema.into()))
-                .map_err(Into::into),
+                .map(|schema| Some(schema.into())),
             None => Ok(None),
         }
     }
 
+    #[graphql(guard = "ScopeGuard(Scope::ReadSolution)")]
     async fn solution<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<String>> {
-        ctx.require_scope(Scope::ReadSolution)?;
+        let sub = ctx.require_sub()?;
 
         tracing::debug!("Running GraphQL query 'question.solution'");
         let pool = ctx.data::<Pool<Postgres>>()?;
 
-        db::get_question_solution(pool, self.id)
+        let solution = db::get_question_solution(pool, self.id).await?;
+
+        if solution.is_some() {
+            tracing::debug!(question_id = self.id, sub, "Recording solution view");
+            db::get_or_initialize_user(pool, sub).await?;
+            db::create_solution_event(pool, sub, self.id).await?;
+        }
+
+        Ok(solution)
+    }
+
+    /// The queries the current user has executed against this question, from
+    /// the newest to the oldest.
+    async fn my_attempts<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+        status: Option<AttemptStatus>,
+    ) -> Result<Connection<AttemptCursor, AttemptEvent, AttemptConnectionFields>> {
+        let sub = ctx.require_sub()?;
+
+        tracing::debug!("Running GraphQL query 'question.my_attempts'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        let filter = db::AttemptFilter {
+            question_id: Some(self.id),
+            status: status.map(Into::into),
+        };
+        event::list_attempts_connection(pool, sub.to_string(), filter, after, before, first, last)
             .await
-            .map_err(Into::into)
     }
 }
 
@@ -122,3 +387,13 @@ impl From<db::Difficulty> for Difficulty {
         }
     }
 }
+
+impl From<Difficulty> for db::Difficulty {
+    fn from(difficulty: Difficulty) -> Self {
+        match difficulty {
+            Difficulty::Easy => Self::Easy,
+            Difficulty::Medium => Self::Medium,
+            Difficulty::Hard => Self::Hard,
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! Limit how often and how many SQL executions a user can run.
+//!
+//! Each user has a token bucket which is refilled at a constant rate, and
+//! every execution takes a token from it. Besides, the executions of a user
+//! that are still waiting for dbrunner are capped.
+//!
+//! Streaming a result with `queryRows` counts as an execution, while the
+//! fields of an execution result are resolved under the permit of the
+//! execution.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_graphql::ErrorExtensions;
+use ecow::EcoString;
+
+use super::error;
+
+/// How often the idle buckets are dropped from the memory.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimiterConfig {
+    /// The number of executions a user can run in a burst.
+    pub burst: u32,
+    /// The number of tokens refilled per minute.
+    pub per_minute: u32,
+    /// The number of executions of a user that can run at the same time.
+    pub max_concurrent: u32,
+}
+
+impl Default for RateLimiterConfig {
+    fn default() -> Self {
+        Self {
+            burst: 10,
+            per_minute: 30,
+            max_concurrent: 2,
+        }
+    }
+}
+
+impl RateLimiterConfig {
+    /// Read the configuration from the `EXECUTION_RATE_LIMIT_BURST`,
+    /// `EXECUTION_RATE_LIMIT_PER_MINUTE` and `EXECUTION_MAX_CONCURRENT`
+    /// environment variables, falling back to the default values.
+    ///
+    /// Every value must be a positive integer.
+    pub fn from_env() -> Result<Self, InvalidConfig> {
+        fn var(name: &'static str, default: u32) -> Result<u32, InvalidConfig> {
+            parse_var(name, std::env::var(name).ok(), default)
+        }
+
+        let default = Self::default();
+        Ok(Self {
+            burst: var("EXECUTION_RATE_LIMIT_BURST", default.burst)?,
+            per_minute: var("EXECUTION_RATE_LIMIT_PER_MINUTE", default.per_minute)?,
+            max_concurrent: var("EXECUTION_MAX_CONCURRENT", default.max_concurrent)?,
+        })
+    }
+
+    /// The time to refill a token.
+    fn refill_interval(&self) -> Duration {
+        Duration::from_secs(60) / self.per_minute.max(1)
+    }
+}
+
+/// Parse the value of the environment variable `name`, or take `default` if
+/// it is not set.
+fn parse_var(
+    name: &'static str,
+    value: Option<String>,
+    default: u32,
+) -> Result<u32, InvalidConfig> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+
+    value
+        .parse::<NonZeroU32>()
+        .map(NonZeroU32::get)
+        .map_err(|_| InvalidConfig {
+            name,
+            value: value.into(),
+        })
+}
+
+/// An environment variable of [`RateLimiterConfig`] is not a positive integer.
+#[derive(thiserror::Error, Debug)]
+#[error("invalid {name} environment variable, a positive integer is expected: {value:?}")]
+pub struct InvalidConfig {
+    name: &'static str,
+    value: EcoString,
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: RateLimiterConfig,
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    buckets: HashMap<String, Bucket>,
+    pruned_at: Instant,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    in_flight: u32,
+}
+
+impl Bucket {
+    fn refill(&mut self, config: &RateLimiterConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        let refilled = elapsed.as_secs_f64() / config.refill_interval().as_secs_f64();
+
+        self.tokens = (self.tokens + refilled).min(config.burst as f64);
+        self.refilled_at = now;
+    }
+}
+
+/// The execution is rejected by [`RateLimiter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimited {
+    /// The user has run out of tokens.
+    TooManyRequests { retry_after: Duration },
+    /// The user has too many executions in flight.
+    TooManyConcurrent { retry_after: Duration },
+}
+
+/// The permission to run an execution. The in-flight slot is released on drop.
+pub struct ExecutionPermit {
+    state: Arc<Mutex<State>>,
+    sub: String,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimiterConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(State {
+                buckets: HashMap::new(),
+                pruned_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Take a token and an in-flight slot of `sub`.
+    pub fn acquire(&self, sub: &str) -> Result<ExecutionPermit, RateLimited> {
+        self.acquire_at(sub, Instant::now())
+    }
+
+    fn acquire_at(&self, sub: &str, now: Instant) -> Result<ExecutionPermit, RateLimited> {
+        let config = &self.config;
+        let mut state = self.state.lock().expect("rate limiter poisoned");
+
+        if now.saturating_duration_since(state.pruned_at) >= PRUNE_INTERVAL {
+            state.buckets.retain(|_, bucket| {
+                bucket.refill(config, now);
+                bucket.in_flight > 0 || bucket.tokens < config.burst as f64
+            });
+            state.pruned_at = now;
+        }
+
+        let bucket = state.buckets.entry(sub.to_string()).or_insert(Bucket {
+            tokens: config.burst as f64,
+            refilled_at: now,
+            in_flight: 0,
+        });
+        bucket.refill(config, now);
+
+        if bucket.in_flight >= config.max_concurrent {
+            return Err(RateLimited::TooManyConcurrent {
+                retry_after: Duration::from_secs(1),
+            });
+        }
+        if bucket.tokens < 1.0 {
+            let missing = 1.0 - bucket.tokens;
+            return Err(RateLimited::TooManyRequests {
+                retry_after: config.refill_interval().mul_f64(missing),
+            });
+        }
+
+        bucket.tokens -= 1.0;
+        bucket.in_flight += 1;
+
+        Ok(ExecutionPermit {
+            state: self.state.clone(),
+            sub: sub.to_string(),
+        })
+    }
+}
+
+impl Drop for ExecutionPermit {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().expect("rate limiter poisoned");
+        if let Some(bucket) = state.buckets.get_mut(&self.sub) {
+            bucket.in_flight = bucket.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+impl RateLimited {
+    pub fn retry_after(&self) -> Duration {
+        match self {
+            Self::TooManyRequests { retry_after } | Self::TooManyConcurrent { retry_after } => {
+                *retry_after
+            }
+        }
+    }
+}
+
+impl From<RateLimited> for async_graphql::Error {
+    fn from(value: RateLimited) -> Self {
+        let details = match value {
+            RateLimited::TooManyRequests { .. } => "You are running queries too frequently.",
+            RateLimited::TooManyConcurrent { .. } => "You have too many queries running.",
+        };
+        // Round up so that retrying after the hint always succeeds.
+        let retry_after = value.retry_after().as_millis().div_ceil(1000) as u64;
+
+        error::Error {
+            code: error::ErrorCode::RateLimited,
+            title: EcoString::inline("Rate limited"),
+            details: Cow::Borrowed(details),
+            error: None,
+        }
+        .to_gql_error()
+        .extend_with(|_, eev| eev.set("retryAfter", retry_after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{RateLimited, RateLimiter, RateLimiterConfig, parse_var};
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimiterConfig {
+            burst: 2,
+            per_minute: 6,
+            max_concurrent: 10,
+        })
+    }
+
+    #[test]
+    fn test_burst_then_limited() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        let _a = limiter.acquire_at("user", now).expect("first");
+        let _b = limiter.acquire_at("user", now).expect("second");
+
+        let limited = limiter.acquire_at("user", now).err().expect("limited");
+        assert_eq!(
+            limited,
+            RateLimited::TooManyRequests {
+                retry_after: Duration::from_secs(10)
+            }
+        );
+    }
+
+    #[test]
+    fn test_refill() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        limiter.acquire_at("user", now).expect("token");
+        limiter.acquire_at("user", now).expect("token");
+        assert!(limiter.acquire_at("user", now).is_err());
+
+        let later = now + Duration::from_secs(10);
+        assert!(limiter.acquire_at("user", later).is_ok());
+        assert!(limiter.acquire_at("user", later).is_err());
+    }
+
+    #[test]
+    fn test_keyed_by_user() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        limiter.acquire_at("user", now).expect("token");
+        limiter.acquire_at("user", now).expect("token");
+        assert!(limiter.acquire_at("user", now).is_err());
+        assert!(limiter.acquire_at("another", now).is_ok());
+    }
+
+    #[test]
+    fn test_concurrency_cap() {
+        let limiter = RateLimiter::new(RateLimiterConfig {
+            burst: 10,
+            per_minute: 60,
+            max_concurrent: 1,
+        });
+        let now = Instant::now();
+
+        let permit = limiter.acquire_at("user", now).expect("first");
+        assert!(matches!(
+            limiter.acquire_at("user", now),
+            Err(RateLimited::TooManyConcurrent { .. })
+        ));
+
+        drop(permit);
+        assert!(limiter.acquire_at("user", now).is_ok());
+    }
+
+    #[test]
+    fn test_parse_var() {
+        assert_eq!(parse_var("VAR", None, 3).unwrap(), 3);
+        assert_eq!(parse_var("VAR", Some("5".into()), 3).unwrap(), 5);
+
+        for value in ["0", "-1", "many"] {
+            assert!(parse_var("VAR", Some(value.into()), 3).is_err(), "{value}");
+        }
+    }
+}
@@ -1,12 +1,24 @@
-use async_graphql::{ComplexObject, Context, Object, Result, SimpleObject};
+use async_graphql::{
+    ComplexObject, Context, InputObject, MaybeUndefined, Object, Result, SimpleObject,
+    connection::{self, Connection, Edge, OpaqueCursor},
+};
 use chrono::Utc;
 
 use crate::{
     db,
-    gql::auth::{ContextAuthExt, Scope},
+    gql::{
+        auth::{Role, Scope},
+        guard::{RoleGuard, ScopeGuard},
+    },
+};
+
+use super::{
+    error,
+    questions::{self, Question, QuestionConnectionFields, QuestionCursor, QuestionOrder},
 };
 
-use super::error;
+/// The opaque cursor of a schema, which wraps the schema ID.
+pub type SchemaCursor = OpaqueCursor<String>;
 
 #[derive(Default)]
 pub struct SchemaQuery;
@@ -15,9 +27,8 @@ impl SchemaQuery {}
 
 #[Object]
 impl SchemaQuery {
+    #[graphql(guard = "ScopeGuard(Scope::ReadPublicResource)")]
     async fn schema<'ctx>(&self, ctx: &Context<'ctx>, id: String) -> Result<Schema> {
-        ctx.require_scope(Scope::ReadPublicResource)?;
-
         tracing::debug!("Running GraphQL query 'schema'");
         let pool = ctx.data::<db::Pool>()?;
 
@@ -26,6 +37,168 @@ impl SchemaQuery {
             .map(Into::into)
             .map_err(error::gqlize)
     }
+
+    #[graphql(guard = "ScopeGuard(Scope::ReadPublicResource)")]
+    async fn schemas<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<SchemaCursor, Schema, SchemaConnectionFields>> {
+        tracing::debug!("Running GraphQL query 'schemas'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        connection::query(
+            after,
+            before,
+            first,
+            last,
+            |after: Option<SchemaCursor>, before: Option<SchemaCursor>, first, last| async move {
+                let cursor = db::KeysetCursor {
+                    after: after.map(|cursor| cursor.0),
+                    before: before.map(|cursor| cursor.0),
+                    first: first.map(|n| n as i64),
+                    last: last.map(|n| n as i64),
+                };
+                let page = db::list_schemas(pool, cursor)
+                    .await
+                    .map_err(error::gqlize)?;
+
+                let mut connection = Connection::with_additional_fields(
+                    page.has_previous_page,
+                    page.has_next_page,
+                    SchemaConnectionFields,
+                );
+                connection
+                    .edges
+                    .extend(page.items.into_iter().map(|schema| {
+                        Edge::new(OpaqueCursor(schema.schema_id.clone()), schema.into())
+                    }));
+
+                Ok::<_, async_graphql::Error>(connection)
+            },
+        )
+        .await
+    }
+}
+
+pub struct SchemaConnectionFields;
+
+#[Object]
+impl SchemaConnectionFields {
+    /// The number of schemas in all pages.
+    async fn total_count<'ctx>(&self, ctx: &Context<'ctx>) -> Result<i64> {
+        tracing::debug!("Running GraphQL query 'schemas.total_count'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::count_schemas(pool).await.map_err(error::gqlize)
+    }
+}
+
+#[derive(Default)]
+pub struct SchemaMutation;
+
+#[Object]
+impl SchemaMutation {
+    #[graphql(guard = "ScopeGuard(Scope::WriteResource).and(RoleGuard(Role::Admin))")]
+    async fn create_schema<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        input: CreateSchemaInput,
+    ) -> Result<Schema> {
+        tracing::debug!("Running GraphQL mutation 'create_schema'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::create_schema(
+            pool,
+            db::SchemaCreateParameter {
+                schema_id: &input.id,
+                picture: input.picture.as_deref(),
+                description: input.description.as_deref(),
+                initial_sql: &input.initial_sql,
+            },
+        )
+        .await
+        .map_err(error::gqlize)?;
+
+        db::get_schema(pool, &input.id)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+
+    #[graphql(guard = "ScopeGuard(Scope::WriteResource).and(RoleGuard(Role::Admin))")]
+    async fn update_schema<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: String,
+        input: UpdateSchemaInput,
+    ) -> Result<Schema> {
+        tracing::debug!("Running GraphQL mutation 'update_schema'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::update_schema(
+            pool,
+            &id,
+            db::SchemaUpdateParameter {
+                picture: input.picture.as_opt_deref(),
+                description: input.description.as_deref(),
+                initial_sql: input.initial_sql.as_deref(),
+            },
+        )
+        .await
+        .map_err(error::gqlize)?;
+
+        db::get_schema(pool, &id)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+
+    /// Mark the schema as deleted. Returns the ID of the deleted schema.
+    ///
+    /// The schemas still used by a question cannot be deleted.
+    #[graphql(guard = "ScopeGuard(Scope::WriteResource).and(RoleGuard(Role::Admin))")]
+    async fn delete_schema<'ctx>(&self, ctx: &Context<'ctx>, id: String) -> Result<String> {
+        tracing::debug!("Running GraphQL mutation 'delete_schema'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::delete_schema(pool, &id).await.map_err(error::gqlize)?;
+
+        Ok(id)
+    }
+
+    #[graphql(guard = "ScopeGuard(Scope::WriteResource).and(RoleGuard(Role::Admin))")]
+    async fn restore_schema<'ctx>(&self, ctx: &Context<'ctx>, id: String) -> Result<Schema> {
+        tracing::debug!("Running GraphQL mutation 'restore_schema'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::restore_schema(pool, &id).await.map_err(error::gqlize)?;
+
+        db::get_schema(pool, &id)
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+}
+
+#[derive(InputObject)]
+pub struct CreateSchemaInput {
+    pub id: String,
+    pub picture: Option<String>,
+    pub description: Option<String>,
+    pub initial_sql: String,
+}
+
+/// The fields to update. The omitted fields are left unchanged.
+#[derive(InputObject)]
+pub struct UpdateSchemaInput {
+    /// Set to `null` to remove the picture.
+    pub picture: MaybeUndefined<String>,
+    pub description: Option<String>,
+    pub initial_sql: Option<String>,
 }
 
 #[derive(Debug, SimpleObject)]
@@ -60,4 +233,25 @@ impl Schema {
             .await
             .map_err(error::gqlize)
     }
+
+    /// The questions using this schema.
+    #[graphql(guard = "ScopeGuard(Scope::ReadPublicResource)")]
+    async fn questions<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+        #[graphql(default)] order: QuestionOrder,
+    ) -> Result<Connection<QuestionCursor, Question, QuestionConnectionFields>> {
+        tracing::debug!("Running GraphQL query 'schema.questions'");
+        let pool = ctx.data::<db::Pool>()?;
+        let filter = db::QuestionFilter {
+            schema_id: Some(self.id.clone()),
+            ..Default::default()
+        };
+
+        questions::list_questions_connection(pool, filter, order, after, before, first, last).await
+    }
 }
@@ -5,87 +5,312 @@ use crate::{
     gql::{
         auth::{ContextAuthExt, Scope},
         error,
+        guard::ScopeGuard,
+        rate_limit::{ExecutionPermit, RateLimiter},
     },
     rpc::{
         self,
         dbrunner::{
-            retrieve_query_response::Kind, run_query_response::ResponseType,
-            AreQueriesOutputSameRequest, RetrieveQueryRequest, RetrieveQueryResponse,
-            RunQueryRequest,
+            AreQueriesOutputSameRequest, DataRow, DiffQueryRequest, RetrieveQueryRequest,
+            RetrieveQueryResponse, RunQueryRequest, retrieve_query_response::Kind,
+            run_query_response::ResponseType,
         },
     },
 };
-use async_graphql::{ComplexObject, Context, Object, Result, SimpleObject, Union};
+use async_graphql::{
+    ComplexObject, Context, ID, Object, Result, SimpleObject, Subscription, Union,
+};
 use ecow::EcoString;
+use futures_util::{Stream, TryStreamExt, stream};
+use tokio::sync::OnceCell;
 
 #[derive(Default)]
 pub struct SqlExecutorMutation;
 
 #[Object]
 impl SqlExecutorMutation {
+    #[graphql(guard = "ScopeGuard(Scope::Execution)")]
     pub async fn execute<'ctx>(
         &self,
         ctx: &Context<'ctx>,
         question_id: i64,
         sql: String,
     ) -> Result<ExecuteResult> {
-        ctx.require_scope(Scope::Execution)?;
+        let sub = ctx.require_sub()?;
 
         let pool = ctx.data::<db::Pool>()?;
-        let mut dbrunner = ctx.rpc_client()?;
+        let dbrunner = ctx.rpc_client()?;
+
+        // Hold the permit until dbrunner has finished running the query, and
+        // the fields of the result have been resolved.
+        let permit = ctx.data::<RateLimiter>()?.acquire(sub)?;
 
         tracing::debug!(question_id, "Retrieving initial SQL");
         let initial_sql = db::get_question_schema_initial_sql(pool, question_id)
             .await
             .map_err(error::gqlize)?;
 
+        tracing::debug!(question_id, sub, "Recording attempt");
+        db::get_or_initialize_user(pool, sub)
+            .await
+            .map_err(error::gqlize)?;
+        let attempt_event_id =
+            db::create_attempt_event(pool, sub, question_id, &sql, db::AttemptStatus::Pending)
+                .await
+                .map_err(error::gqlize)?;
+
         tracing::debug!(initial_sql, sql, "Running user query");
-        let result = dbrunner
+        let result = match dbrunner
             .run_query(RunQueryRequest {
                 schema: initial_sql.clone(),
                 query: sql,
             })
             .await
-            .map_err(|e| match e {
-                _ if e.code() == tonic::Code::InvalidArgument => Error::InvalidQuery(e),
-                _ => Error::RetrieveFailed(e),
-            })?;
+        {
+            Ok(result) => result,
+            Err(e) => {
+                let message = match e {
+                    rpc::CallError::Status(ref status) => status.message().to_string(),
+                    ref e => e.to_string(),
+                };
+                db::mark_attempt_event_failed(pool, attempt_event_id, &message)
+                    .await
+                    .map_err(error::gqlize)?;
+
+                return Err(match e {
+                    rpc::CallError::Status(status)
+                        if status.code() == tonic::Code::InvalidArgument =>
+                    {
+                        Error::InvalidQuery(Box::new(status))
+                    }
+                    e => e.into(),
+                }
+                .into());
+            }
+        };
 
         tracing::debug!(question_id, "Constructing response");
         match result.into_inner().response_type {
             Some(ResponseType::Id(user_query_id)) => {
-                Ok(ExecuteResult::Success(ExecuteSuccessResult {
+                db::set_attempt_event_query_id(pool, attempt_event_id, &user_query_id)
+                    .await
+                    .map_err(error::gqlize)?;
+
+                Ok(ExecuteResult::Success(Box::new(ExecuteSuccessResult {
+                    question_id,
+                    attempt_event_id,
                     initial_sql,
                     user_query_id,
-                }))
+                    answer_query_id: OnceCell::new(),
+                    table: OnceCell::new(),
+                    judged: OnceCell::new(),
+                    result_diff: OnceCell::new(),
+                    _permit: permit,
+                })))
             }
             Some(ResponseType::Error(error)) => {
+                db::mark_attempt_event_failed(pool, attempt_event_id, &error)
+                    .await
+                    .map_err(error::gqlize)?;
+
                 Ok(ExecuteResult::Failed(ExecuteFailedResult { error }))
             }
-            None => Err(Error::InvalidResponseType.into()),
+            None => {
+                db::mark_attempt_event_failed(pool, attempt_event_id, "Unknown response type.")
+                    .await
+                    .map_err(error::gqlize)?;
+
+                Err(Error::InvalidResponseType.into())
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SqlExecutorSubscription;
+
+#[Subscription]
+impl SqlExecutorSubscription {
+    /// Stream the result of an executed query.
+    ///
+    /// The header is sent first, and then the rows are sent in batches of
+    /// `batchSize` as soon as dbrunner produces them. Only the queries executed
+    /// by the user can be streamed. It is rate limited as an execution until the
+    /// stream ends.
+    #[graphql(guard = "ScopeGuard(Scope::Execution)")]
+    async fn query_rows<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        query_id: ID,
+        #[graphql(default = 100, validator(minimum = 1, maximum = 1000))] batch_size: usize,
+    ) -> Result<impl Stream<Item = Result<TableChunk>>> {
+        let sub = ctx.require_sub()?;
+
+        let pool = ctx.data::<db::Pool>()?;
+        let dbrunner = ctx.rpc_client()?;
+
+        tracing::debug!(query_id = query_id.as_str(), sub, "Checking query owner");
+        let owned = db::is_query_owned_by(pool, sub, &query_id)
+            .await
+            .map_err(error::gqlize)?;
+        if !owned {
+            return Err(error::gqlize(db::Error::NotFound {
+                entity: "query",
+                id: query_id.as_str().into(),
+            }));
+        }
+
+        let permit = ctx.data::<RateLimiter>()?.acquire(sub)?;
+
+        tracing::debug!(query_id = query_id.as_str(), "Streaming query results");
+        let query_response = dbrunner
+            .retrieve_query(RetrieveQueryRequest {
+                id: query_id.to_string(),
+            })
+            .await
+            .map_err(Error::from)?;
+        let query_response_body = query_response.into_inner();
+
+        let state = TableStream {
+            body: query_response_body,
+            header_seen: false,
+            _permit: permit,
+        };
+        let chunks = stream::try_unfold(Some(state), move |state| {
+            next_table_chunk(state, batch_size)
+        });
+
+        Ok(chunks.map_err(Into::into))
+    }
+}
+
+/// The stream of query results read by `queryRows`.
+struct TableStream {
+    body: tonic::Streaming<RetrieveQueryResponse>,
+    /// Whether the header has been read. It must come once, before any row.
+    header_seen: bool,
+    _permit: ExecutionPermit,
+}
+
+/// Read the next chunk from the stream of query results.
+///
+/// `state` is `None` if the stream has been drained.
+async fn next_table_chunk(
+    state: Option<TableStream>,
+    batch_size: usize,
+) -> Result<Option<(TableChunk, Option<TableStream>)>, Error> {
+    let Some(mut state) = state else {
+        return Ok(None);
+    };
+
+    let mut rows = Vec::new();
+    loop {
+        let message = state
+            .body
+            .message()
+            .await
+            .map_err(|e| Error::RetrieveFailed(Box::new(e)))?;
+
+        let Some(RetrieveQueryResponse { kind }) = message else {
+            // Flush the remaining rows.
+            if rows.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some((TableChunk::Rows(TableRows { rows }), None)));
+        };
+
+        match kind.ok_or(Error::InvalidResponseType)? {
+            Kind::Header(_) if state.header_seen => return Err(Error::InvalidResponseType),
+            Kind::Header(header) => {
+                state.header_seen = true;
+
+                let chunk = TableChunk::Header(TableHeader {
+                    column: header.cells,
+                });
+                return Ok(Some((chunk, Some(state))));
+            }
+            Kind::Row(_) if !state.header_seen => return Err(Error::InvalidResponseType),
+            Kind::Row(data_row) => {
+                rows.push(data_row.cells.into_iter().map(|c| c.value).collect());
+
+                if rows.len() >= batch_size {
+                    let chunk = TableChunk::Rows(TableRows { rows });
+                    return Ok(Some((chunk, Some(state))));
+                }
+            }
         }
     }
 }
 
 #[derive(Union)]
 pub enum ExecuteResult {
-    Success(ExecuteSuccessResult),
+    Success(Box<ExecuteSuccessResult>),
     Failed(ExecuteFailedResult),
 }
 
 #[derive(SimpleObject)]
 #[graphql(complex)]
 pub struct ExecuteSuccessResult {
+    #[graphql(visible = false)]
+    question_id: i64,
+    #[graphql(visible = false)]
+    attempt_event_id: i64,
     #[graphql(visible = false)]
     initial_sql: String,
     #[graphql(visible = false)]
     user_query_id: String,
+    /// The ID of the output of the answer, shared by `same` and `diff`.
+    #[graphql(skip)]
+    answer_query_id: OnceCell<String>,
+    // The fields are resolved once, so selecting them again with aliases
+    // does not call dbrunner again.
+    #[graphql(skip)]
+    table: OnceCell<Table>,
+    #[graphql(skip)]
+    judged: OnceCell<bool>,
+    #[graphql(skip)]
+    result_diff: OnceCell<ResultDiff>,
+    /// The permit of the execution, held until the fields are resolved.
+    #[graphql(skip)]
+    _permit: ExecutionPermit,
 }
 
 #[ComplexObject]
 impl ExecuteSuccessResult {
+    /// The ID of the query result, which can be streamed with the
+    /// `queryRows` subscription.
+    async fn query_id(&self) -> ID {
+        ID(self.user_query_id.clone())
+    }
+
     async fn rows<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Table> {
-        let mut dbrunner = ctx.rpc_client()?;
+        self.table
+            .get_or_try_init(|| self.retrieve_rows(ctx))
+            .await
+            .cloned()
+    }
+
+    #[graphql(guard = "ScopeGuard(Scope::ReadAnswer)")]
+    async fn same<'ctx>(&self, ctx: &Context<'ctx>) -> Result<bool> {
+        self.judged
+            .get_or_try_init(|| self.judge(ctx))
+            .await
+            .copied()
+    }
+
+    /// Explain how the result differs from the output of the answer.
+    #[graphql(guard = "ScopeGuard(Scope::ReadAnswer)")]
+    async fn diff<'ctx>(&self, ctx: &Context<'ctx>) -> Result<ResultDiff> {
+        self.result_diff
+            .get_or_try_init(|| self.diff_answer(ctx))
+            .await
+            .cloned()
+    }
+}
+
+impl ExecuteSuccessResult {
+    async fn retrieve_rows(&self, ctx: &Context<'_>) -> Result<Table> {
+        let dbrunner = ctx.rpc_client()?;
 
         tracing::debug!(query_id = self.user_query_id, "Retrieving query results");
         let query_response = dbrunner
@@ -93,7 +318,7 @@ impl ExecuteSuccessResult {
                 id: self.user_query_id.clone(),
             })
             .await
-            .map_err(Error::RetrieveFailed)?;
+            .map_err(Error::from)?;
         let mut query_response_body = query_response.into_inner();
 
         tracing::debug!("Streaming and constructing table");
@@ -104,7 +329,7 @@ impl ExecuteSuccessResult {
             let Some(RetrieveQueryResponse { kind }) = query_response_body
                 .message()
                 .await
-                .map_err(Error::RetrieveFailed)?
+                .map_err(|e| Error::RetrieveFailed(Box::new(e)))?
             else {
                 break;
             };
@@ -121,45 +346,37 @@ impl ExecuteSuccessResult {
         Ok(Table { column, rows })
     }
 
-    async fn same<'ctx>(&self, ctx: &Context<'ctx>) -> Result<bool> {
-        ctx.require_scope(Scope::ReadAnswer)?;
-
+    /// Compare the result with the output of the answer, and grade the
+    /// attempt.
+    async fn judge(&self, ctx: &Context<'_>) -> Result<bool> {
         let pool = ctx.data::<db::Pool>()?;
-        let mut dbrunner = ctx.rpc_client()?;
+        let dbrunner = ctx.rpc_client()?;
 
         tracing::debug!(query_id = self.user_query_id, "Checking answer");
-        let answer = db::get_question_answer(pool, 1)
-            .await
-            .map_err(error::gqlize)?;
-
-        tracing::debug!(
-            initial_sql = self.initial_sql,
-            answer,
-            "Running answer query"
-        );
-        let result = dbrunner
-            .run_query(RunQueryRequest {
-                schema: self.initial_sql.clone(),
-                query: answer,
-            })
-            .await
-            .map_err(Error::RetrieveFailed)?;
-        let answer_sql_id = match result.into_inner().response_type {
-            Some(ResponseType::Id(user_query_id)) => user_query_id,
-            Some(ResponseType::Error(error)) => return Err(Error::AnswerInvalid { error }.into()),
-            None => return Err(Error::InvalidResponseType.into()),
-        };
+        let answer_sql_id = self.answer_query_id(pool, &dbrunner).await?;
 
         tracing::debug!(answer_sql_id, "Comparing results");
         let comparison_result = dbrunner
             .are_queries_output_same(AreQueriesOutputSameRequest {
                 left_id: self.user_query_id.clone(),
-                right_id: answer_sql_id.clone(),
+                right_id: answer_sql_id.to_string(),
             })
             .await
-            .map_err(Error::RetrieveFailed)?;
+            .map_err(Error::from)?;
         let same = comparison_result.into_inner().same;
 
+        let status = if same {
+            db::AttemptStatus::Passed
+        } else {
+            db::AttemptStatus::Failed
+        };
+        let result = if same { "passed" } else { "failed" };
+        metrics::counter!("attempts_judged_total", "result" => result).increment(1);
+
+        db::mark_attempt_event(pool, self.attempt_event_id, status)
+            .await
+            .map_err(error::gqlize)?;
+
         tracing::debug!(
             same,
             left_id = self.user_query_id,
@@ -168,14 +385,135 @@ impl ExecuteSuccessResult {
         );
         Ok(same)
     }
+
+    async fn diff_answer(&self, ctx: &Context<'_>) -> Result<ResultDiff> {
+        let pool = ctx.data::<db::Pool>()?;
+        let dbrunner = ctx.rpc_client()?;
+
+        tracing::debug!(query_id = self.user_query_id, "Diffing answer");
+        let answer_sql_id = self.answer_query_id(pool, &dbrunner).await?;
+
+        tracing::debug!(answer_sql_id, "Diffing results");
+        let diff = dbrunner
+            .diff_query(DiffQueryRequest {
+                left_id: self.user_query_id.clone(),
+                right_id: answer_sql_id.to_string(),
+            })
+            .await
+            .map_err(Error::from)?
+            .into_inner();
+
+        let into_rows = |rows: Vec<DataRow>| -> Vec<Vec<Option<String>>> {
+            rows.into_iter()
+                .map(|row| row.cells.into_iter().map(|c| c.value).collect())
+                .collect()
+        };
+
+        Ok(ResultDiff {
+            missing_rows: into_rows(diff.missing_rows),
+            extra_rows: into_rows(diff.extra_rows),
+            column_mismatches: diff
+                .column_mismatches
+                .into_iter()
+                .map(|mismatch| ColumnMismatch {
+                    index: mismatch.index,
+                    actual: mismatch.left,
+                    expected: mismatch.right,
+                })
+                .collect(),
+            order_differs: diff.order_differs,
+        })
+    }
+
+    /// Get the ID of the output of the answer, which is run on the first use.
+    async fn answer_query_id(
+        &self,
+        pool: &db::Pool,
+        dbrunner: &rpc::DbRunnerClient,
+    ) -> Result<&str, Error> {
+        self.answer_query_id
+            .get_or_try_init(|| self.run_answer_query(pool, dbrunner))
+            .await
+            .map(String::as_str)
+    }
+
+    /// Run the answer of the question, and return the ID of its output.
+    async fn run_answer_query(
+        &self,
+        pool: &db::Pool,
+        dbrunner: &rpc::DbRunnerClient,
+    ) -> Result<String, Error> {
+        let answer = db::get_question_answer(pool, self.question_id)
+            .await
+            .map_err(|e| Error::GenericError(error::gqlize(e)))?;
+
+        tracing::debug!(
+            initial_sql = self.initial_sql,
+            answer,
+            "Running answer query"
+        );
+        let result = dbrunner
+            .run_query(RunQueryRequest {
+                schema: self.initial_sql.clone(),
+                query: answer,
+            })
+            .await
+            .map_err(Error::from)?;
+
+        match result.into_inner().response_type {
+            Some(ResponseType::Id(answer_sql_id)) => Ok(answer_sql_id),
+            Some(ResponseType::Error(error)) => Err(Error::AnswerInvalid { error }),
+            None => Err(Error::InvalidResponseType),
+        }
+    }
 }
 
-#[derive(SimpleObject)]
+#[derive(SimpleObject, Clone)]
 pub struct Table {
     pub column: Vec<String>,
     pub rows: Vec<Vec<Option<String>>>,
 }
 
+/// A part of the query result streamed by the `queryRows` subscription.
+#[derive(Union)]
+pub enum TableChunk {
+    Header(TableHeader),
+    Rows(TableRows),
+}
+
+#[derive(SimpleObject)]
+pub struct TableHeader {
+    pub column: Vec<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct TableRows {
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+/// The differences between the result and the output of the answer.
+#[derive(SimpleObject, Clone)]
+pub struct ResultDiff {
+    /// The rows expected but not in the result.
+    pub missing_rows: Vec<Vec<Option<String>>>,
+    /// The rows in the result but not expected.
+    pub extra_rows: Vec<Vec<Option<String>>>,
+    /// The columns whose names are different from the expected ones.
+    pub column_mismatches: Vec<ColumnMismatch>,
+    /// Whether the result has the expected rows in a different order.
+    pub order_differs: bool,
+}
+
+#[derive(SimpleObject, Clone)]
+pub struct ColumnMismatch {
+    /// The 0-based position of the column.
+    pub index: u32,
+    /// The column name in the result, or `null` if the result has fewer columns.
+    pub actual: Option<String>,
+    /// The expected column name, or `null` if the result has more columns.
+    pub expected: Option<String>,
+}
+
 #[derive(SimpleObject)]
 pub struct ExecuteFailedResult {
     pub error: String,
@@ -184,8 +522,8 @@ pub struct ExecuteFailedResult {
 pub enum Error {
     /// The generic error of async-graphql.
     GenericError(async_graphql::Error),
-    InvalidQuery(tonic::Status),
-    RetrieveFailed(tonic::Status),
+    InvalidQuery(Box<tonic::Status>),
+    RetrieveFailed(Box<tonic::Status>),
     InvalidResponseType,
     DbrunnerUnavailable,
     AnswerInvalid {
@@ -193,6 +531,15 @@ pub enum Error {
     },
 }
 
+impl From<rpc::CallError> for Error {
+    fn from(value: rpc::CallError) -> Self {
+        match value {
+            rpc::CallError::CircuitOpen => Error::DbrunnerUnavailable,
+            rpc::CallError::Status(status) => Error::RetrieveFailed(Box::new(status)),
+        }
+    }
+}
+
 impl From<Error> for async_graphql::Error {
     fn from(value: Error) -> Self {
         match value {
@@ -201,14 +548,14 @@ impl From<Error> for async_graphql::Error {
                 code: error::ErrorCode::InvalidQuery,
                 title: EcoString::inline("Invalid query"),
                 details: e.message().to_string().into(),
-                error: Some(Box::new(e)),
+                error: Some(e),
             }
             .to_gql_error(),
             Error::RetrieveFailed(e) => error::Error {
                 code: error::ErrorCode::InternalError,
                 title: EcoString::inline("Internal error"),
                 details: Cow::Borrowed("Unable to retrieve results from dbrunner."),
-                error: Some(Box::new(e)),
+                error: Some(e),
             }
             .to_gql_error(),
             Error::InvalidResponseType => error::Error {
@@ -1,10 +1,22 @@
-use async_graphql::{ComplexObject, Context, Object, Result, SimpleObject};
-use ecow::EcoString;
+use async_graphql::{
+    ComplexObject, Context, Object, Result, SimpleObject, connection::Connection,
+    dataloader::DataLoader,
+};
 
-use super::error::Error;
-use crate::{db, gql::error::ErrorCode};
+use crate::db;
 
-use super::auth::ContextAuthExt;
+use super::{
+    auth::{ContextAuthExt, Role, Scope},
+    error,
+    event::{
+        self, AttemptConnectionFields, AttemptCursor, AttemptEvent, AttemptFilter,
+        SolutionConnectionFields, SolutionCursor, SolutionEvent,
+    },
+    group::Group,
+    guard::{RoleGuard, ScopeGuard, UserGuard},
+    loader::{self, GroupLoader},
+    progress::Progress,
+};
 
 #[derive(Default)]
 pub struct UserQuery;
@@ -14,27 +26,47 @@ impl UserQuery {
     async fn user<'ctx>(&self, ctx: &Context<'ctx>) -> Result<User> {
         tracing::debug!("Running GraphQL query 'user'");
 
-        let Some(sub) = ctx.sub() else {
-            return Err(Error {
-                code: ErrorCode::Unauthorized,
-                title: EcoString::inline("Unauthorized"),
-                details: "You must login to access this API.".into(),
-                error: None,
-            }
-            .to_gql_error());
-        };
+        let sub = ctx.require_sub()?;
         let pool = ctx.data::<db::Pool>()?;
 
-        let user = db::get_or_initialize_user(pool, sub).await?;
+        let user = db::get_or_initialize_user(pool, sub)
+            .await
+            .map_err(error::gqlize)?;
         Ok(user.into())
     }
 }
 
+#[derive(Default)]
+pub struct UserMutation;
+
+#[Object]
+impl UserMutation {
+    /// Change the role stored for the user. The role in the token claims
+    /// still applies if it is higher.
+    #[graphql(guard = "ScopeGuard(Scope::ManageUser).and(RoleGuard(Role::Admin))")]
+    async fn set_user_role<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        user_id: String,
+        role: Role,
+    ) -> Result<User> {
+        tracing::debug!("Running GraphQL mutation 'set_user_role'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        db::set_user_role(pool, &user_id, role.into())
+            .await
+            .map(Into::into)
+            .map_err(error::gqlize)
+    }
+}
+
 #[derive(Debug, Clone, SimpleObject)]
 #[graphql(complex)]
 pub struct User {
     pub user_id: String,
     pub group_id: Option<i64>,
+    /// The role stored for the user, regardless of the token claims.
+    pub role: Role,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -44,6 +76,7 @@ impl From<db::User> for User {
         Self {
             user_id: user.user_id,
             group_id: user.group_id,
+            role: user.role.into(),
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
@@ -59,30 +92,64 @@ impl User {
             return Ok(None);
         };
 
-        let pool = ctx.data::<db::Pool>()?;
-        let group = db::get_group(pool, group_id).await?;
+        let loader = ctx.data::<DataLoader<GroupLoader>>()?;
+        let group = loader::load_one(loader, "group", group_id).await?;
 
         Ok(Some(group.into()))
     }
-}
 
-#[derive(SimpleObject)]
-pub struct Group {
-    pub group_id: i64,
-    pub name: String,
-    pub description: String,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-    pub updated_at: chrono::DateTime<chrono::Utc>,
-}
+    /// The solution videos this user has revealed, from the newest to the oldest.
+    #[graphql(guard = "UserGuard::new(&self.user_id)")]
+    async fn viewed_solutions<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<SolutionCursor, SolutionEvent, SolutionConnectionFields>> {
+        tracing::debug!("Running GraphQL query 'user.viewed_solutions'");
+        let pool = ctx.data::<db::Pool>()?;
 
-impl From<db::Group> for Group {
-    fn from(group: db::Group) -> Self {
-        Self {
-            group_id: group.group_id,
-            name: group.name,
-            description: group.description,
-            created_at: group.created_at,
-            updated_at: group.updated_at,
-        }
+        event::list_solutions_connection(pool, self.user_id.clone(), after, before, first, last)
+            .await
+    }
+
+    /// The queries this user has executed, from the newest to the oldest.
+    #[graphql(guard = "UserGuard::new(&self.user_id)")]
+    async fn attempts<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+        #[graphql(default)] filter: AttemptFilter,
+    ) -> Result<Connection<AttemptCursor, AttemptEvent, AttemptConnectionFields>> {
+        tracing::debug!("Running GraphQL query 'user.attempts'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        event::list_attempts_connection(
+            pool,
+            self.user_id.clone(),
+            filter.into(),
+            after,
+            before,
+            first,
+            last,
+        )
+        .await
+    }
+
+    /// The progress of this user on the questions.
+    #[graphql(guard = "UserGuard::new(&self.user_id)")]
+    async fn progress<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Progress> {
+        tracing::debug!("Running GraphQL query 'user.progress'");
+        let pool = ctx.data::<db::Pool>()?;
+
+        let breakdown = db::get_progress_breakdown(pool, &self.user_id)
+            .await
+            .map_err(error::gqlize)?;
+        Ok(Progress::new(self.user_id.clone(), breakdown))
     }
 }
@@ -0,0 +1,87 @@
+//! Cache the `dp_users` rows loaded for the authenticated requests.
+//!
+//! Every request checks whether its user has been banned or deleted, so the
+//! rows are kept for a short while instead of querying them each time. A ban
+//! or a role change takes effect once the entry expires.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::db;
+
+/// How often the expired entries are dropped from the memory.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+pub struct UserCache {
+    ttl: Duration,
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    /// The user, or `None` if the user has not been initialized.
+    entries: HashMap<String, (Instant, Option<db::User>)>,
+    pruned_at: Instant,
+}
+
+impl Default for UserCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl UserCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Arc::new(Mutex::new(State {
+                entries: HashMap::new(),
+                pruned_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Get the user from the cache, or load it from the database.
+    pub async fn get(
+        &self,
+        conn: impl db::Executor<'_>,
+        user_id: &str,
+    ) -> Result<Option<db::User>, db::Error> {
+        if let Some(user) = self.cached(user_id, Instant::now()) {
+            return Ok(user);
+        }
+
+        let user = db::get_user(conn, user_id).await?;
+
+        let now = Instant::now();
+        let mut state = self.state.lock().expect("user cache poisoned");
+        if now.saturating_duration_since(state.pruned_at) >= PRUNE_INTERVAL {
+            let ttl = self.ttl;
+            state
+                .entries
+                .retain(|_, (loaded_at, _)| now.saturating_duration_since(*loaded_at) < ttl);
+            state.pruned_at = now;
+        }
+        state
+            .entries
+            .insert(user_id.to_string(), (now, user.clone()));
+
+        Ok(user)
+    }
+
+    /// Drop the cached user, for example after the user is deleted.
+    pub fn invalidate(&self, user_id: &str) {
+        let mut state = self.state.lock().expect("user cache poisoned");
+        state.entries.remove(user_id);
+    }
+
+    fn cached(&self, user_id: &str, now: Instant) -> Option<Option<db::User>> {
+        let state = self.state.lock().expect("user cache poisoned");
+        let (loaded_at, user) = state.entries.get(user_id)?;
+
+        (now.saturating_duration_since(*loaded_at) < self.ttl).then(|| user.clone())
+    }
+}
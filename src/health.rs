@@ -0,0 +1,103 @@
+//! Report whether the service and its dependencies are working.
+
+use std::{future::Future, time::Duration};
+
+use poem::{IntoResponse, handler, http::StatusCode, web::Data, web::Json};
+use serde::Serialize;
+
+use crate::{db, rpc};
+
+/// The time to wait for each dependency to respond.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub status: Status,
+    pub postgres: Check,
+    pub dbrunner: Check,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Error,
+}
+
+/// The process is up and able to serve requests.
+#[handler]
+pub async fn live() -> impl IntoResponse {
+    "OK"
+}
+
+/// The dependencies are reachable, so the instance can take traffic.
+///
+/// Responds 503 Service Unavailable if any dependency fails.
+#[handler]
+pub async fn ready(
+    pool: Data<&db::Pool>,
+    dbrunner: Data<&Option<rpc::DbRunnerClient>>,
+) -> impl IntoResponse {
+    let (postgres, dbrunner) = tokio::join!(
+        check(async {
+            sqlx::query("SELECT 1")
+                .execute(pool.0)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        }),
+        check(async {
+            match dbrunner.0 {
+                Some(dbrunner) => dbrunner.probe().await.map_err(|e| e.to_string()),
+                None => Err("dbrunner is not configured".to_string()),
+            }
+        }),
+    );
+
+    let status = if postgres.status == Status::Ok && dbrunner.status == Status::Ok {
+        Status::Ok
+    } else {
+        Status::Error
+    };
+    let code = match status {
+        Status::Ok => StatusCode::OK,
+        Status::Error => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (
+        code,
+        Json(Readiness {
+            status,
+            postgres,
+            dbrunner,
+        }),
+    )
+}
+
+async fn check(probe: impl Future<Output = Result<(), String>>) -> Check {
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, probe).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {CHECK_TIMEOUT:?}")),
+    };
+
+    match result {
+        Ok(()) => Check {
+            status: Status::Ok,
+            error: None,
+        },
+        Err(error) => {
+            tracing::warn!(error, "Readiness check failed");
+            Check {
+                status: Status::Error,
+                error: Some(error),
+            }
+        }
+    }
+}
@@ -1,3 +1,7 @@
 pub mod db;
 pub mod gql;
+pub mod health;
+pub mod logto;
+pub mod metrics;
+pub mod migrate;
 pub mod rpc;
@@ -0,0 +1,187 @@
+//! The client of the Logto Management API.
+//!
+//! It authenticates as a machine-to-machine application with the client
+//! credentials flow, and caches the access token until it is about to expire.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ecow::EcoString;
+use reqwest::{StatusCode, Url};
+use tokio::sync::Mutex;
+
+/// The resource indicator of the Management API of the self-hosted Logto.
+const DEFAULT_RESOURCE: &str = "https://default.logto.app/api";
+
+/// The time to wait for establishing a connection to Logto.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The deadline of each request to Logto.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Renew the access token this long before it expires.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct LogtoConfig {
+    /// The URL of the Logto server, ending with a slash.
+    pub endpoint: Url,
+    pub app_id: String,
+    pub app_secret: String,
+    /// The resource indicator of the Management API.
+    pub resource: String,
+    /// The deadline of each request to Logto.
+    pub timeout: Duration,
+}
+
+impl LogtoConfig {
+    /// Read the configuration from `LOGTO_DOMAIN`, `LOGTO_MANAGEMENT_APP_ID`,
+    /// `LOGTO_MANAGEMENT_APP_SECRET` and `LOGTO_MANAGEMENT_RESOURCE`.
+    pub fn from_env() -> Result<Self, Error> {
+        fn var(name: &str) -> Result<String, Error> {
+            std::env::var(name).map_err(|_| Error::MissingConfig(name.into()))
+        }
+
+        let domain = var("LOGTO_DOMAIN")?;
+        Ok(Self {
+            endpoint: Url::parse(&domain).map_err(|_| Error::InvalidEndpoint(domain.into()))?,
+            app_id: var("LOGTO_MANAGEMENT_APP_ID")?,
+            app_secret: var("LOGTO_MANAGEMENT_APP_SECRET")?,
+            resource: std::env::var("LOGTO_MANAGEMENT_RESOURCE")
+                .unwrap_or_else(|_| DEFAULT_RESOURCE.to_string()),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct LogtoClient {
+    http: reqwest::Client,
+    config: Arc<LogtoConfig>,
+    token: Arc<Mutex<Option<AccessToken>>>,
+}
+
+struct AccessToken {
+    token: String,
+    expires_at: Instant,
+}
+
+impl LogtoClient {
+    pub fn new(config: LogtoConfig) -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .timeout(config.timeout)
+                .build()
+                .expect("failed to build HTTP client"),
+            config: Arc::new(config),
+            token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Delete the user from Logto. A user which does not exist is ignored.
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_user(&self, user_id: &str) -> Result<(), Error> {
+        // The ID comes from the token, so it is escaped as a single segment
+        // and cannot point the request to another endpoint.
+        if matches!(user_id, "" | "." | "..") {
+            return Err(Error::InvalidUserId(user_id.into()));
+        }
+        let mut url = self.url("api/users/")?;
+        url.path_segments_mut()
+            .map_err(|_| Error::InvalidEndpoint(self.config.endpoint.as_str().into()))?
+            .pop_if_empty()
+            .push(user_id);
+
+        let token = self.access_token().await?;
+
+        let response = self
+            .http
+            .delete(url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(Error::Request)?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => {
+                tracing::warn!("User does not exist in Logto");
+                Ok(())
+            }
+            status => Err(Error::UnexpectedStatus(status)),
+        }
+    }
+
+    async fn access_token(&self) -> Result<String, Error> {
+        #[derive(serde::Deserialize)]
+        struct TokenResponse {
+            access_token: String,
+            expires_in: u64,
+        }
+
+        // Held while fetching, so the concurrent requests share the same token.
+        // The fetch is bounded by the timeout of the client.
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref().filter(|t| Instant::now() < t.expires_at) {
+            return Ok(token.token.clone());
+        }
+
+        tracing::debug!("Requesting Logto Management API access token");
+        let response = self
+            .http
+            .post(self.url("oidc/token")?)
+            .basic_auth(&self.config.app_id, Some(&self.config.app_secret))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("resource", self.config.resource.as_str()),
+                ("scope", "all"),
+            ])
+            .send()
+            .await
+            .map_err(Error::Request)?;
+
+        if !response.status().is_success() {
+            return Err(Error::UnexpectedStatus(response.status()));
+        }
+        let response = response
+            .json::<TokenResponse>()
+            .await
+            .map_err(Error::Request)?;
+
+        let lifetime = Duration::from_secs(response.expires_in).saturating_sub(EXPIRY_MARGIN);
+        *cached = Some(AccessToken {
+            token: response.access_token.clone(),
+            expires_at: Instant::now() + lifetime,
+        });
+
+        Ok(response.access_token)
+    }
+
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.config
+            .endpoint
+            .join(path)
+            .map_err(|_| Error::InvalidEndpoint(path.into()))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("missing {0} environment variable")]
+    MissingConfig(EcoString),
+
+    #[error("invalid Logto endpoint: {0}")]
+    InvalidEndpoint(EcoString),
+
+    #[error("invalid Logto user ID: {0:?}")]
+    InvalidUserId(EcoString),
+
+    #[error("request Logto: {0}")]
+    Request(reqwest::Error),
+
+    #[error("unexpected status from Logto: {0}")]
+    UnexpectedStatus(StatusCode),
+}
@@ -1,10 +1,21 @@
 use std::net::SocketAddr;
 
-use async_graphql::{extensions::Tracing, http::GraphiQLSource, EmptySubscription, Schema};
+use async_graphql::{Schema, dataloader::DataLoader, extensions::Tracing, http::GraphiQLSource};
 use backend::{
-    gql::{self, auth::AuthBuilder},
-    rpc,
+    db,
+    gql::{
+        self,
+        auth::{AuthBuilder, JwksConfig, LogtoProvider, OidcProvider, SharedSecretProvider},
+        loader::{GroupLoader, QuestionLoader, SchemaLoader},
+        metrics::Metrics,
+        rate_limit::{RateLimiter, RateLimiterConfig},
+        user_cache::UserCache,
+    },
+    health,
+    logto::{LogtoClient, LogtoConfig},
+    metrics, migrate, rpc,
 };
+use clap::{Parser, Subcommand};
 use middleware::Cors;
 use mimalloc_rust::GlobalMiMalloc;
 use poem::{listener::TcpListener, web::Html, *};
@@ -14,50 +25,215 @@ static GLOBAL_MIMALLOC: GlobalMiMalloc = GlobalMiMalloc;
 
 #[handler]
 async fn graphiql() -> impl IntoResponse {
-    Html(GraphiQLSource::build().finish())
+    Html(
+        GraphiQLSource::build()
+            .endpoint("/")
+            .subscription_endpoint("/ws")
+            .finish(),
+    )
 }
 
-#[handler]
-async fn health() -> impl IntoResponse {
-    "OK"
+/// Choose the authentication provider by `AUTH_PROVIDER`.
+///
+/// - `logto` (default): `LOGTO_DOMAIN` and `LOGTO_RESOURCE_INDICATOR`.
+/// - `oidc`: `OIDC_ISSUER` and `OIDC_AUDIENCE`.
+/// - `shared-secret`: `AUTH_SHARED_SECRET`, for the local development only.
+async fn auth_builder() -> Result<AuthBuilder, Box<dyn std::error::Error>> {
+    fn var(name: &str) -> Result<String, String> {
+        std::env::var(name).map_err(|_| format!("missing {name} environment variable"))
+    }
+
+    let provider = std::env::var("AUTH_PROVIDER").unwrap_or_else(|_| "logto".to_string());
+
+    let auth_builder = match provider.as_str() {
+        "logto" => {
+            let logto_domain = var("LOGTO_DOMAIN")?;
+            let logto_resource_indicator = var("LOGTO_RESOURCE_INDICATOR")?.into();
+
+            AuthBuilder::new(LogtoProvider::new(
+                &logto_domain,
+                logto_resource_indicator,
+                JwksConfig::default(),
+            ))
+        }
+        "oidc" => {
+            let issuer = var("OIDC_ISSUER")?;
+            let audience = var("OIDC_AUDIENCE")?.into();
+
+            AuthBuilder::new(
+                OidcProvider::discover(&issuer, audience, JwksConfig::default()).await?,
+            )
+        }
+        "shared-secret" => {
+            let secret = var("AUTH_SHARED_SECRET")?;
+            tracing::warn!(
+                "Using a shared secret to verify the tokens. Do not use it in production."
+            );
+
+            AuthBuilder::new(SharedSecretProvider::new(secret.as_bytes()))
+        }
+        provider => return Err(format!("unknown AUTH_PROVIDER: {provider}").into()),
+    };
+
+    Ok(auth_builder)
+}
+
+#[derive(Parser)]
+#[command(about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The arguments of `serve`, which runs without a subcommand.
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Serve the GraphQL API. This is the default command.
+    Serve(ServeArgs),
+    /// Manage the database migrations embedded in the binary.
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommand,
+    },
+    /// Create a group, and print its ID.
+    CreateGroup(CreateGroupArgs),
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Apply the pending migrations before serving.
+    #[arg(long, env = "MIGRATE_ON_STARTUP")]
+    migrate: bool,
+}
+
+#[derive(Subcommand)]
+enum MigrateCommand {
+    /// Apply the pending migrations.
+    Up,
+    /// List the migrations and whether they have been applied.
+    Status,
+}
+
+#[derive(clap::Args)]
+struct CreateGroupArgs {
+    #[arg(long)]
+    name: String,
+    #[arg(long)]
+    description: Option<String>,
+    /// The user ID of the teacher who manages the group.
+    #[arg(long)]
+    owner_id: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Serve(cli.serve)) {
+        Command::Serve(args) => serve(args).await,
+        Command::Migrate { command } => run_migrate(command).await,
+        Command::CreateGroup(args) => create_group(args).await,
+    }
+}
+
+async fn run_migrate(command: MigrateCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = db::pool().await?;
+
+    match command {
+        MigrateCommand::Up => {
+            migrate::up(&pool).await?;
+            tracing::info!("Applied the pending migrations");
+        }
+        MigrateCommand::Status => {
+            for migration in migrate::status(&pool).await? {
+                println!(
+                    "{version}\t{state}\t{description}",
+                    version = migration.version,
+                    state = migration.state,
+                    description = migration.description,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn create_group(args: CreateGroupArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = db::pool().await?;
+
+    if let Some(owner_id) = &args.owner_id {
+        db::get_or_initialize_user(&pool, owner_id).await?;
+    }
+
+    let group_id = db::create_group(
+        &pool,
+        db::GroupCreateParameter {
+            name: &args.name,
+            description: args.description.as_deref(),
+            owner_id: args.owner_id.as_deref(),
+        },
+    )
+    .await?;
+
+    println!("{group_id}");
+    Ok(())
+}
+
+async fn serve(args: ServeArgs) -> Result<(), Box<dyn std::error::Error>> {
     let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
     let addr = SocketAddr::from(([0, 0, 0, 0], port.parse::<u16>().expect("invalid port")));
 
-    let logto_domain = std::env::var("LOGTO_DOMAIN")
-        .expect("LOGTO_DOMAIN must be set")
-        .into();
-    let logto_resource_indicator = std::env::var("LOGTO_RESOURCE_INDICATOR")
-        .expect("LOGTO_RESOURCE_INDICATOR must be set")
-        .into();
-    let auth_builder = AuthBuilder {
-        logto_domain,
-        resource_indicator: logto_resource_indicator,
-    };
+    let auth_builder = auth_builder().await?;
+    let rate_limit = RateLimiterConfig::from_env()?;
+
+    let metrics_handle = metrics::install()?;
+
+    let pool = db::pool().await?;
+    if args.migrate {
+        tracing::info!("Applying the pending migrations");
+        migrate::up(&pool).await?;
+    }
+
+    let dbrunner = rpc::dbrunner_client(rpc::DbRunnerConfig::default())
+        .inspect_err(|e| {
+            tracing::warn!(
+                error = ?e,
+                "Failed to configure dbrunner. Disabling SQL execution."
+            );
+        })
+        .ok();
+
+    let logto = LogtoConfig::from_env()
+        .map(LogtoClient::new)
+        .inspect_err(|e| {
+            tracing::warn!(
+                error = ?e,
+                "Failed to configure Logto Management API. Deleted accounts are kept in Logto."
+            );
+        })
+        .ok();
+    let user_cache = UserCache::default();
 
     let schema = Schema::build(
         gql::Query::default(),
         gql::Mutation::default(),
-        EmptySubscription,
-    )
-    .data(backend::db::pool().await?)
-    .data(
-        rpc::dbrunner_client()
-            .await
-            .inspect_err(|e| {
-                tracing::warn!(
-                    error = ?e,
-                    "Failed to connect to dbrunner. Disabling SQL execution."
-                );
-            })
-            .ok(),
+        gql::Subscription::default(),
     )
+    .data(DataLoader::new(SchemaLoader(pool.clone()), tokio::spawn))
+    .data(DataLoader::new(GroupLoader(pool.clone()), tokio::spawn))
+    .data(DataLoader::new(QuestionLoader(pool.clone()), tokio::spawn))
+    .data(pool.clone())
+    .data(dbrunner.clone())
+    .data(RateLimiter::new(rate_limit))
+    .data(logto)
+    .data(user_cache.clone())
     .extension(Tracing)
+    .extension(Metrics)
     .finish();
 
     let origin = std::env::var("FRONTEND_CORS_ORIGIN")
@@ -74,10 +250,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let app = Route::new()
         .at("/", get(graphiql).post(gql::poem::index))
-        .at("/health", get(health))
+        .at("/ws", get(gql::poem::subscription))
+        .at("/health", get(health::live))
+        .at("/health/live", get(health::live))
+        .at("/health/ready", get(health::ready))
+        .at("/metrics", get(metrics::render))
         .with(cors)
         .data(auth_builder)
-        .data(schema);
+        .data(schema)
+        .data(pool)
+        .data(user_cache)
+        .data(dbrunner)
+        .data(metrics_handle);
 
     tracing::info!(
         "GraphiQL: http://127.0.0.1:{port}. Listened on {addr}",
@@ -0,0 +1,55 @@
+//! Export the metrics of the service in the Prometheus text format.
+
+use std::time::Duration;
+
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use poem::{handler, web::Data};
+
+use crate::db;
+
+/// The buckets of the `*_seconds` histograms.
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// The interval to drain the histograms.
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Install the Prometheus recorder as the global recorder.
+///
+/// It must be called within a Tokio runtime, which runs the upkeep of the
+/// recorder in the background.
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".into()), DURATION_BUCKETS)?
+        .install_recorder()?;
+
+    let upkeep_handle = handle.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            upkeep_handle.run_upkeep();
+        }
+    });
+
+    Ok(handle)
+}
+
+/// Render the metrics in the Prometheus text format.
+///
+/// The statistics of the connection pool are sampled on each scrape.
+#[handler]
+pub async fn render(handle: Data<&PrometheusHandle>, pool: Data<&db::Pool>) -> String {
+    record_pool_stats(pool.0);
+    handle.render()
+}
+
+fn record_pool_stats(pool: &db::Pool) {
+    let idle = pool.num_idle();
+    let in_use = (pool.size() as usize).saturating_sub(idle);
+
+    metrics::gauge!("db_pool_connections", "state" => "idle").set(idle as f64);
+    metrics::gauge!("db_pool_connections", "state" => "in_use").set(in_use as f64);
+    metrics::gauge!("db_pool_max_connections").set(pool.options().get_max_connections());
+}
@@ -0,0 +1,99 @@
+//! Apply the migrations in `migrations/`, which are embedded in the binary.
+//!
+//! The applied migrations are recorded in `_sqlx_migrations`, the same table
+//! `sqlx migrate` uses, so both ways can be mixed.
+
+use std::collections::HashMap;
+
+use sqlx::migrate::{MigrateError, Migrator};
+
+use crate::db;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// The migration has been applied, but the file has changed since then.
+    ChecksumMismatch,
+    /// The migration has been applied, but it is not embedded in this binary.
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+/// Apply the pending migrations.
+pub async fn up(pool: &db::Pool) -> Result<(), MigrateError> {
+    MIGRATOR.run(pool).await
+}
+
+/// Compare the embedded migrations with the applied ones.
+///
+/// It only reads the database. If `_sqlx_migrations` does not exist, every
+/// migration is pending.
+pub async fn status(pool: &db::Pool) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+
+    let mut applied = HashMap::new();
+    if exists {
+        let rows: Vec<(i64, String, Vec<u8>)> =
+            sqlx::query_as("SELECT version, description, checksum FROM _sqlx_migrations")
+                .fetch_all(pool)
+                .await?;
+        applied.extend(
+            rows.into_iter()
+                .map(|(version, description, checksum)| (version, (description, checksum))),
+        );
+    }
+
+    let mut statuses = MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| {
+            let state = match applied.remove(&migration.version) {
+                None => MigrationState::Pending,
+                Some((_, checksum)) if *checksum == *migration.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::ChecksumMismatch,
+            };
+
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // The rest have been applied by a newer binary or removed from the files.
+    statuses.extend(
+        applied
+            .into_iter()
+            .map(|(version, (description, _))| MigrationStatus {
+                version,
+                description,
+                state: MigrationState::Unknown,
+            }),
+    );
+    statuses.sort_by_key(|status| status.version);
+
+    Ok(statuses)
+}
+
+impl std::fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationState::Applied => write!(f, "applied"),
+            MigrationState::Pending => write!(f, "pending"),
+            MigrationState::ChecksumMismatch => write!(f, "checksum mismatch"),
+            MigrationState::Unknown => write!(f, "unknown"),
+        }
+    }
+}
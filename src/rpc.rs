@@ -2,18 +2,285 @@ pub mod dbrunner {
     tonic::include_proto!("dbrunner.v1");
 }
 
-use dbrunner::db_runner_service_client::DbRunnerServiceClient;
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use dbrunner::{
+    AreQueriesOutputSameRequest, AreQueriesOutputSameResponse, DiffQueryRequest, DiffQueryResponse,
+    RetrieveQueryRequest, RetrieveQueryResponse, RunQueryRequest, RunQueryResponse,
+    db_runner_service_client::DbRunnerServiceClient,
+};
 use ecow::EcoString;
+use tonic::{Response, Status, Streaming, transport::Channel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbRunnerConfig {
+    /// The time to wait for establishing a connection.
+    pub connect_timeout: Duration,
+    /// The deadline of each call.
+    pub timeout: Duration,
+    /// The number of retries of an idempotent call.
+    pub max_retries: u32,
+    /// The delay before the first retry, which is doubled on each retry.
+    pub backoff: Duration,
+    /// The number of consecutive failures to trip the circuit breaker.
+    pub failure_threshold: u32,
+    /// The time to reject the calls after the circuit breaker trips.
+    pub cooldown: Duration,
+}
+
+impl Default for DbRunnerConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(3),
+            timeout: Duration::from_secs(30),
+            max_retries: 2,
+            backoff: Duration::from_millis(100),
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(10),
+        }
+    }
+}
 
-pub type DbRunnerClient = DbRunnerServiceClient<tonic::transport::Channel>;
+/// The client of dbrunner.
+///
+/// The channel connects on the first call and reconnects in the background
+/// once the connection is lost. The idempotent calls are retried with backoff,
+/// and all calls are rejected at once while the circuit breaker is open.
+#[derive(Clone)]
+pub struct DbRunnerClient {
+    client: DbRunnerServiceClient<Channel>,
+    config: DbRunnerConfig,
+    breaker: Arc<CircuitBreaker>,
+}
 
-pub async fn dbrunner_client() -> Result<DbRunnerClient, Error> {
+/// Create a dbrunner client for the address in `DBRUNNER_ADDR`.
+pub fn dbrunner_client(config: DbRunnerConfig) -> Result<DbRunnerClient, Error> {
     let addr = std::env::var("DBRUNNER_ADDR").map_err(|_| Error::NoClientAddress {
         client: "DBRUNNER_ADDR".into(),
     })?;
-    let client = DbRunnerServiceClient::connect(addr).await?;
 
-    return Ok(client);
+    DbRunnerClient::connect_lazy(addr, config)
+}
+
+impl DbRunnerClient {
+    /// Create a client which connects to `addr` on the first call.
+    pub fn connect_lazy(addr: String, config: DbRunnerConfig) -> Result<Self, Error> {
+        let channel = Channel::from_shared(addr)
+            .map_err(|e| Error::InvalidUri(e.to_string().into()))?
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.timeout)
+            .connect_lazy();
+
+        Ok(Self {
+            client: DbRunnerServiceClient::new(channel),
+            config,
+            breaker: Arc::new(CircuitBreaker::new(
+                config.failure_threshold,
+                config.cooldown,
+            )),
+        })
+    }
+
+    /// Run the query. It is not retried since it creates a new result.
+    pub async fn run_query(
+        &self,
+        request: RunQueryRequest,
+    ) -> Result<Response<RunQueryResponse>, CallError> {
+        self.call("run_query", false, |mut client| {
+            let request = request.clone();
+            async move { client.run_query(request).await }
+        })
+        .await
+    }
+
+    pub async fn retrieve_query(
+        &self,
+        request: RetrieveQueryRequest,
+    ) -> Result<Response<Streaming<RetrieveQueryResponse>>, CallError> {
+        self.call("retrieve_query", true, |mut client| {
+            let request = request.clone();
+            async move { client.retrieve_query(request).await }
+        })
+        .await
+    }
+
+    pub async fn are_queries_output_same(
+        &self,
+        request: AreQueriesOutputSameRequest,
+    ) -> Result<Response<AreQueriesOutputSameResponse>, CallError> {
+        self.call("are_queries_output_same", true, |mut client| {
+            let request = request.clone();
+            async move { client.are_queries_output_same(request).await }
+        })
+        .await
+    }
+
+    pub async fn diff_query(
+        &self,
+        request: DiffQueryRequest,
+    ) -> Result<Response<DiffQueryResponse>, CallError> {
+        self.call("diff_query", true, |mut client| {
+            let request = request.clone();
+            async move { client.diff_query(request).await }
+        })
+        .await
+    }
+
+    /// Check if dbrunner responds, bypassing the retries and the circuit
+    /// breaker.
+    ///
+    /// Any response counts, including the error of looking up an unknown
+    /// query.
+    pub async fn probe(&self) -> Result<(), CallError> {
+        let request = RetrieveQueryRequest {
+            id: "health-probe".to_string(),
+        };
+
+        match self.client.clone().retrieve_query(request).await {
+            Err(status) if is_unavailable(&status) => Err(CallError::Status(status)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Call `method` and record its outcome in the metrics.
+    async fn call<T, F, Fut>(
+        &self,
+        method: &'static str,
+        idempotent: bool,
+        f: F,
+    ) -> Result<T, CallError>
+    where
+        F: FnMut(DbRunnerServiceClient<Channel>) -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let start = Instant::now();
+        let result = self.call_with_retry(idempotent, f).await;
+
+        let status = match &result {
+            Ok(_) => "ok",
+            Err(CallError::CircuitOpen) => "circuit_open",
+            Err(CallError::Status(status)) if is_unavailable(status) => "unavailable",
+            Err(CallError::Status(status)) if status.code() == tonic::Code::DeadlineExceeded => {
+                "deadline_exceeded"
+            }
+            Err(CallError::Status(_)) => "error",
+        };
+        metrics::counter!("dbrunner_calls_total", "method" => method, "status" => status)
+            .increment(1);
+        metrics::histogram!("dbrunner_call_duration_seconds", "method" => method)
+            .record(start.elapsed());
+
+        result
+    }
+
+    async fn call_with_retry<T, F, Fut>(&self, idempotent: bool, mut f: F) -> Result<T, CallError>
+    where
+        F: FnMut(DbRunnerServiceClient<Channel>) -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let max_retries = if idempotent {
+            self.config.max_retries
+        } else {
+            0
+        };
+
+        let mut attempt = 0;
+        loop {
+            if !self.breaker.allow(Instant::now()) {
+                return Err(CallError::CircuitOpen);
+            }
+
+            match f(self.client.clone()).await {
+                Err(status) if is_unavailable(&status) => {
+                    self.breaker.record_failure(Instant::now());
+
+                    if attempt >= max_retries {
+                        return Err(CallError::Status(status));
+                    }
+
+                    let delay = self.config.backoff * 2u32.pow(attempt);
+                    tracing::debug!(attempt, ?delay, %status, "Retrying dbrunner call");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                // A slow query does not mean dbrunner is down, and running it
+                // again would most likely time out as well.
+                Err(status) if status.code() == tonic::Code::DeadlineExceeded => {
+                    return Err(CallError::Status(status));
+                }
+                result => {
+                    // dbrunner has responded, even if it is an error.
+                    self.breaker.record_success();
+                    return result.map_err(CallError::Status);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the call failed because dbrunner is not reachable.
+fn is_unavailable(status: &Status) -> bool {
+    status.code() == tonic::Code::Unavailable
+}
+
+/// Reject the calls for a while after too many consecutive failures.
+struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    /// The time the breaker tripped, or `None` if it is closed.
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Whether a call is allowed at `now`.
+    ///
+    /// Once the cooldown has passed, the calls are let through again; a
+    /// single failure trips the breaker again until a call succeeds.
+    fn allow(&self, now: Instant) -> bool {
+        let state = self.state.lock().expect("circuit breaker poisoned");
+        match state.opened_at {
+            Some(opened_at) => now.saturating_duration_since(opened_at) >= self.cooldown,
+            None => true,
+        }
+    }
+
+    fn record_failure(&self, now: Instant) {
+        let mut state = self.state.lock().expect("circuit breaker poisoned");
+        state.failures += 1;
+
+        if state.failures >= self.failure_threshold {
+            if state.opened_at.is_none() {
+                tracing::warn!(failures = state.failures, "dbrunner is unavailable");
+            }
+            state.opened_at = Some(now);
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().expect("circuit breaker poisoned");
+        if state.opened_at.is_some() {
+            tracing::info!("dbrunner is available again");
+        }
+        *state = BreakerState::default();
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -21,6 +288,121 @@ pub enum Error {
     #[error("no {client} address specified in environment")]
     NoClientAddress { client: EcoString },
 
+    #[error("invalid dbrunner address: {0}")]
+    InvalidUri(EcoString),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CallError {
+    #[error("dbrunner is unavailable")]
+    CircuitOpen,
+
     #[error(transparent)]
-    TransportError(#[from] tonic::transport::Error),
+    Status(#[from] Status),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::{Duration, Instant},
+    };
+
+    use tonic::Status;
+
+    use super::{CallError, CircuitBreaker, DbRunnerClient, DbRunnerConfig};
+    use crate::rpc::dbrunner::RetrieveQueryRequest;
+
+    #[test]
+    fn test_breaker_trips_after_threshold() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        assert!(breaker.allow(now));
+
+        breaker.record_failure(now);
+        assert!(!breaker.allow(now));
+        assert!(!breaker.allow(now + Duration::from_secs(9)));
+    }
+
+    #[test]
+    fn test_breaker_half_open_after_cooldown() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.allow(later), "a trial call is allowed");
+
+        breaker.record_failure(later);
+        assert!(!breaker.allow(later), "tripped again by a single failure");
+
+        breaker.record_success();
+        assert!(breaker.allow(later));
+    }
+
+    #[test]
+    fn test_breaker_reset_on_success() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert!(breaker.allow(now), "failures are consecutive");
+    }
+
+    #[tokio::test]
+    async fn test_client_fails_fast_when_unreachable() {
+        let client = DbRunnerClient::connect_lazy(
+            // Nothing listens on the discard port.
+            "http://127.0.0.1:9".to_string(),
+            DbRunnerConfig {
+                max_retries: 1,
+                backoff: Duration::from_millis(1),
+                failure_threshold: 2,
+                ..Default::default()
+            },
+        )
+        .expect("invalid address");
+
+        let request = RetrieveQueryRequest { id: "id".into() };
+        let first = client.retrieve_query(request.clone()).await;
+        assert!(matches!(first, Err(CallError::Status(_))), "retried once");
+
+        let second = client.retrieve_query(request).await;
+        assert!(matches!(second, Err(CallError::CircuitOpen)));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_exceeded_not_retried() {
+        let client = DbRunnerClient::connect_lazy(
+            "http://127.0.0.1:9".to_string(),
+            DbRunnerConfig {
+                max_retries: 2,
+                backoff: Duration::from_millis(1),
+                failure_threshold: 1,
+                ..Default::default()
+            },
+        )
+        .expect("invalid address");
+
+        let calls = AtomicU32::new(0);
+        let result = client
+            .call_with_retry(true, |_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                async { Err::<(), _>(Status::deadline_exceeded("slow query")) }
+            })
+            .await;
+
+        assert!(matches!(result, Err(CallError::Status(_))));
+        assert_eq!(calls.load(Ordering::Relaxed), 1, "not retried");
+        assert!(
+            client.breaker.allow(Instant::now()),
+            "the breaker is not tripped"
+        );
+    }
 }
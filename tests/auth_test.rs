@@ -0,0 +1,218 @@
+mod common;
+
+use std::time::Duration;
+
+use backend::gql::auth::{
+    AuthBuilder, AuthError, JwksConfig, LogtoProvider, OidcProvider, Scope, SharedSecretProvider,
+};
+use common::jwks::{MockJwks, RESOURCE_INDICATOR, shared_secret_token};
+use jsonwebtoken::Algorithm;
+
+fn auth_builder(jwks: &MockJwks, config: JwksConfig) -> AuthBuilder {
+    AuthBuilder::new(LogtoProvider::new(
+        &jwks.logto_domain,
+        RESOURCE_INDICATOR.into(),
+        config,
+    ))
+}
+
+#[tokio::test]
+async fn test_select_key_by_kid() {
+    let jwks = MockJwks::serve(&["key-1", "key-2"]).await;
+    let builder = auth_builder(&jwks, JwksConfig::default());
+
+    let token = jwks.token(Some("key-2"), Algorithm::HS256, "user", "execution");
+    let auth = builder.build(&token).await.expect("failed to authenticate");
+    assert_eq!(auth.sub, "user");
+    assert!(auth.has_scope(Scope::Execution));
+
+    let token = jwks.token(Some("key-1"), Algorithm::HS256, "user", "");
+    assert!(builder.build(&token).await.is_ok());
+    assert_eq!(jwks.fetches(), 1, "the set is cached");
+}
+
+#[tokio::test]
+async fn test_token_without_kid() {
+    let jwks = MockJwks::serve(&[""]).await;
+    let builder = auth_builder(&jwks, JwksConfig::default());
+
+    let token = jwks.token(None, Algorithm::HS256, "user", "");
+    assert!(builder.build(&token).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_reject_algorithm_mismatch() {
+    let jwks = MockJwks::serve(&["key-1"]).await;
+    let builder = auth_builder(&jwks, JwksConfig::default());
+
+    // The key is declared for HS256 only.
+    let token = jwks.token(Some("key-1"), Algorithm::HS384, "user", "");
+    let result = builder.build(&token).await;
+    assert!(matches!(result, Err(AuthError::NoMatchingJwk { .. })));
+}
+
+#[tokio::test]
+async fn test_refetch_on_key_rotation() {
+    let jwks = MockJwks::serve(&["key-1"]).await;
+    let builder = auth_builder(
+        &jwks,
+        JwksConfig {
+            refresh_interval: Duration::ZERO,
+            ..Default::default()
+        },
+    );
+
+    let token = jwks.token(Some("key-1"), Algorithm::HS256, "user", "");
+    assert!(builder.build(&token).await.is_ok());
+
+    jwks.rotate(&["key-2"]);
+    let token = jwks.token(Some("key-2"), Algorithm::HS256, "user", "");
+    assert!(builder.build(&token).await.is_ok());
+    assert_eq!(jwks.fetches(), 2);
+}
+
+#[tokio::test]
+async fn test_refetch_rate_limited() {
+    let jwks = MockJwks::serve(&["key-1"]).await;
+    let builder = auth_builder(
+        &jwks,
+        JwksConfig {
+            refresh_interval: Duration::from_millis(200),
+            ..Default::default()
+        },
+    );
+
+    let token = jwks.token(Some("key-1"), Algorithm::HS256, "user", "");
+    assert!(builder.build(&token).await.is_ok());
+
+    let unknown = jwks.token(Some("unknown"), Algorithm::HS256, "user", "");
+    for _ in 0..3 {
+        let result = builder.build(&unknown).await;
+        assert!(matches!(result, Err(AuthError::NoMatchingJwk { .. })));
+    }
+    assert_eq!(jwks.fetches(), 1, "refetch is rate-limited");
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(builder.build(&unknown).await.is_err());
+    assert_eq!(jwks.fetches(), 2, "refetched after the interval");
+}
+
+#[tokio::test]
+async fn test_cached_keys_available_while_fetching() {
+    let jwks = MockJwks::serve(&["key-1"]).await;
+    let builder = auth_builder(
+        &jwks,
+        JwksConfig {
+            refresh_interval: Duration::ZERO,
+            ..Default::default()
+        },
+    );
+
+    let token = jwks.token(Some("key-1"), Algorithm::HS256, "user", "");
+    assert!(builder.build(&token).await.is_ok());
+
+    jwks.hang(true);
+    let unknown = jwks.token(Some("unknown"), Algorithm::HS256, "user", "");
+    let refetch = tokio::spawn({
+        let builder = builder.clone();
+        async move { builder.build(&unknown).await }
+    });
+    while jwks.fetches() < 2 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let result = tokio::time::timeout(Duration::from_secs(1), builder.build(&token)).await;
+    assert!(
+        matches!(result, Ok(Ok(_))),
+        "the cached key is not blocked by the refetch"
+    );
+    refetch.abort();
+}
+
+#[tokio::test]
+async fn test_fetch_timeout() {
+    let jwks = MockJwks::serve(&["key-1"]).await;
+    jwks.hang(true);
+    let builder = auth_builder(
+        &jwks,
+        JwksConfig {
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        },
+    );
+
+    let token = jwks.token(Some("key-1"), Algorithm::HS256, "user", "");
+    let result = tokio::time::timeout(Duration::from_secs(5), builder.build(&token))
+        .await
+        .expect("the fetch is not timed out");
+    assert!(matches!(result, Err(AuthError::GetJwtSetFailed(_))));
+}
+
+#[tokio::test]
+async fn test_retry_after_failed_fetch() {
+    let jwks = MockJwks::serve(&[""]).await;
+    jwks.hang(true);
+    let builder = auth_builder(
+        &jwks,
+        JwksConfig {
+            refresh_interval: Duration::from_millis(200),
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        },
+    );
+
+    let token = jwks.token(None, Algorithm::HS256, "user", "");
+    let result = builder.build(&token).await;
+    assert!(matches!(result, Err(AuthError::GetJwtSetFailed(_))));
+
+    jwks.hang(false);
+    let result = builder.build(&token).await;
+    assert!(matches!(result, Err(AuthError::NoMatchingJwk { .. })));
+    assert_eq!(jwks.fetches(), 1, "the failed fetch is rate-limited");
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(
+        builder.build(&token).await.is_ok(),
+        "the failed fetch is not cached for the TTL"
+    );
+}
+
+#[tokio::test]
+async fn test_oidc_discovery() {
+    let jwks = MockJwks::serve(&["key-1"]).await;
+    let provider = OidcProvider::discover(
+        &jwks.issuer(),
+        RESOURCE_INDICATOR.into(),
+        JwksConfig::default(),
+    )
+    .await
+    .expect("failed to discover");
+    let builder = AuthBuilder::new(provider);
+
+    let token = jwks.token(Some("key-1"), Algorithm::HS256, "user", "read:answer");
+    let auth = builder.build(&token).await.expect("failed to authenticate");
+    assert_eq!(auth.sub, "user");
+    assert!(auth.has_scope(Scope::ReadAnswer));
+}
+
+#[tokio::test]
+async fn test_shared_secret() {
+    let builder = AuthBuilder::new(SharedSecretProvider::new(b"secret"));
+
+    let token = shared_secret_token(b"secret", Algorithm::HS256, "user", "execution");
+    let auth = builder.build(&token).await.expect("failed to authenticate");
+    assert_eq!(auth.sub, "user");
+    assert!(auth.has_scope(Scope::Execution));
+
+    let token = shared_secret_token(b"another", Algorithm::HS256, "user", "");
+    assert!(matches!(
+        builder.build(&token).await,
+        Err(AuthError::DecodeJwt(_))
+    ));
+
+    let token = shared_secret_token(b"secret", Algorithm::HS512, "user", "");
+    assert!(matches!(
+        builder.build(&token).await,
+        Err(AuthError::UnsupportedAlgorithm(Algorithm::HS512))
+    ));
+}
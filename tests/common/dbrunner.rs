@@ -0,0 +1,298 @@
+//! An in-process stand-in of dbrunner.
+//!
+//! Every query runs in a throwaway Postgres schema inside a transaction of the
+//! test database, which is rolled back once the output is collected.
+
+// The service trait generated by tonic returns the unboxed `Status`.
+#![allow(clippy::result_large_err)]
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use backend::rpc::{
+    DbRunnerClient, DbRunnerConfig,
+    dbrunner::{
+        AreQueriesOutputSameRequest, AreQueriesOutputSameResponse, Cell, ColumnMismatch, DataRow,
+        DiffQueryRequest, DiffQueryResponse, HeaderRow, RetrieveQueryRequest,
+        RetrieveQueryResponse, RunQueryRequest, RunQueryResponse,
+        db_runner_service_server::{DbRunnerService, DbRunnerServiceServer},
+        retrieve_query_response::Kind,
+        run_query_response::ResponseType,
+    },
+};
+use futures_util::{Stream, stream};
+use sqlx::{Column, Executor, PgPool, Row, ValueRef};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status};
+
+type Record = Vec<Option<String>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Output {
+    columns: Vec<String>,
+    rows: Vec<Record>,
+}
+
+pub struct MockDbRunner {
+    pool: PgPool,
+    outputs: Mutex<HashMap<String, Output>>,
+    streams: HashMap<String, Vec<Kind>>,
+    untyped: Vec<String>,
+    next_id: AtomicU64,
+    runs: Arc<AtomicUsize>,
+}
+
+impl MockDbRunner {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            outputs: Mutex::new(HashMap::new()),
+            streams: HashMap::new(),
+            untyped: Vec::new(),
+            next_id: AtomicU64::new(1),
+            runs: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// The number of the `run_query` calls, which keeps counting after the
+    /// stand-in is served.
+    pub fn runs(&self) -> Arc<AtomicUsize> {
+        self.runs.clone()
+    }
+
+    /// Make `retrieve_query` of `id` send `messages` as they are, for testing
+    /// the malformed responses.
+    pub fn with_stream(mut self, id: &str, messages: Vec<Kind>) -> Self {
+        self.streams.insert(id.to_string(), messages);
+        self
+    }
+
+    /// Make `run_query` of `query` respond without a response type.
+    pub fn with_untyped_response(mut self, query: &str) -> Self {
+        self.untyped.push(query.to_string());
+        self
+    }
+
+    /// Serve the stand-in on a random local port, and connect to it.
+    pub async fn serve(self) -> DbRunnerClient {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .expect("failed to bind dbrunner");
+        let addr = listener.local_addr().expect("no local address");
+
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(DbRunnerServiceServer::new(self))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        DbRunnerClient::connect_lazy(format!("http://{addr}"), DbRunnerConfig::default())
+            .expect("invalid dbrunner address")
+    }
+
+    fn output(&self, id: &str) -> Result<Output, Status> {
+        self.outputs
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("query {id} not found")))
+    }
+}
+
+#[tonic::async_trait]
+impl DbRunnerService for MockDbRunner {
+    type RetrieveQueryStream =
+        Pin<Box<dyn Stream<Item = Result<RetrieveQueryResponse, Status>> + Send>>;
+
+    async fn run_query(
+        &self,
+        request: Request<RunQueryRequest>,
+    ) -> Result<Response<RunQueryResponse>, Status> {
+        let RunQueryRequest { schema, query } = request.into_inner();
+        self.runs.fetch_add(1, Ordering::SeqCst);
+
+        if self.untyped.contains(&query) {
+            return Ok(Response::new(RunQueryResponse {
+                response_type: None,
+            }));
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let output = run(&self.pool, id, &schema, &query).await?;
+
+        let response_type = match output {
+            Ok(output) => {
+                let id = format!("query-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+                self.outputs.lock().unwrap().insert(id.clone(), output);
+                ResponseType::Id(id)
+            }
+            Err(error) => ResponseType::Error(error),
+        };
+
+        Ok(Response::new(RunQueryResponse {
+            response_type: Some(response_type),
+        }))
+    }
+
+    async fn retrieve_query(
+        &self,
+        request: Request<RetrieveQueryRequest>,
+    ) -> Result<Response<Self::RetrieveQueryStream>, Status> {
+        let id = request.into_inner().id;
+        if let Some(messages) = self.streams.get(&id) {
+            let messages = messages
+                .iter()
+                .map(|kind| {
+                    Ok(RetrieveQueryResponse {
+                        kind: Some(kind.clone()),
+                    })
+                })
+                .collect::<Vec<_>>();
+            return Ok(Response::new(Box::pin(stream::iter(messages))));
+        }
+        let output = self.output(&id)?;
+
+        let header = Kind::Header(HeaderRow {
+            cells: output.columns,
+        });
+        let rows = output
+            .rows
+            .into_iter()
+            .map(|row| Kind::Row(to_data_row(row)));
+        let messages = std::iter::once(header)
+            .chain(rows)
+            .map(|kind| Ok(RetrieveQueryResponse { kind: Some(kind) }))
+            .collect::<Vec<_>>();
+
+        Ok(Response::new(Box::pin(stream::iter(messages))))
+    }
+
+    async fn are_queries_output_same(
+        &self,
+        request: Request<AreQueriesOutputSameRequest>,
+    ) -> Result<Response<AreQueriesOutputSameResponse>, Status> {
+        let AreQueriesOutputSameRequest { left_id, right_id } = request.into_inner();
+        let same = self.output(&left_id)? == self.output(&right_id)?;
+
+        Ok(Response::new(AreQueriesOutputSameResponse { same }))
+    }
+
+    async fn diff_query(
+        &self,
+        request: Request<DiffQueryRequest>,
+    ) -> Result<Response<DiffQueryResponse>, Status> {
+        let DiffQueryRequest { left_id, right_id } = request.into_inner();
+        let left = self.output(&left_id)?;
+        let right = self.output(&right_id)?;
+
+        let column_mismatches = (0..left.columns.len().max(right.columns.len()))
+            .filter(|&i| left.columns.get(i) != right.columns.get(i))
+            .map(|i| ColumnMismatch {
+                index: i as u32,
+                left: left.columns.get(i).cloned(),
+                right: right.columns.get(i).cloned(),
+            })
+            .collect();
+
+        let missing_rows = multiset_difference(&right.rows, &left.rows);
+        let extra_rows = multiset_difference(&left.rows, &right.rows);
+        let order_differs =
+            missing_rows.is_empty() && extra_rows.is_empty() && left.rows != right.rows;
+
+        Ok(Response::new(DiffQueryResponse {
+            missing_rows: missing_rows.into_iter().map(to_data_row).collect(),
+            extra_rows: extra_rows.into_iter().map(to_data_row).collect(),
+            column_mismatches,
+            order_differs,
+        }))
+    }
+}
+
+/// Run `query` on a fresh schema created by `schema`.
+///
+/// Returns `Ok(Err(message))` if the SQL fails to run.
+async fn run(
+    pool: &PgPool,
+    id: u64,
+    schema: &str,
+    query: &str,
+) -> Result<Result<Output, String>, Status> {
+    let internal = |e: sqlx::Error| Status::internal(e.to_string());
+
+    let mut tx = pool.begin().await.map_err(internal)?;
+    let setup = format!("CREATE SCHEMA dbrunner_{id}; SET LOCAL search_path TO dbrunner_{id};");
+    (&mut *tx)
+        .execute(sqlx::raw_sql(&setup))
+        .await
+        .map_err(internal)?;
+
+    if let Err(e) = (&mut *tx).execute(sqlx::raw_sql(schema)).await {
+        return Ok(Err(e.to_string()));
+    }
+
+    // The simple query protocol returns every value as text.
+    let rows = match (&mut *tx).fetch_all(sqlx::raw_sql(query)).await {
+        Ok(rows) => rows,
+        Err(e) => return Ok(Err(e.to_string())),
+    };
+
+    let columns = match rows.first() {
+        Some(row) => row.columns().iter().map(|c| c.name().to_string()).collect(),
+        None => (&mut *tx)
+            .describe(query)
+            .await
+            .map(|d| d.columns().iter().map(|c| c.name().to_string()).collect())
+            .unwrap_or_default(),
+    };
+    let rows = rows
+        .iter()
+        .map(|row| {
+            (0..row.len())
+                .map(|i| {
+                    let value = row.try_get_raw(i).ok()?;
+                    if value.is_null() {
+                        return None;
+                    }
+                    value.as_str().ok().map(str::to_string)
+                })
+                .collect()
+        })
+        .collect();
+
+    tx.rollback().await.map_err(internal)?;
+
+    Ok(Ok(Output { columns, rows }))
+}
+
+/// The rows in `a` but not in `b`, counting the duplicated rows.
+fn multiset_difference(a: &[Record], b: &[Record]) -> Vec<Record> {
+    let mut remaining = HashMap::<&Record, usize>::new();
+    for row in b {
+        *remaining.entry(row).or_default() += 1;
+    }
+
+    a.iter()
+        .filter(|row| match remaining.get_mut(row) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+fn to_data_row(row: Record) -> DataRow {
+    DataRow {
+        cells: row.into_iter().map(|value| Cell { value }).collect(),
+    }
+}
@@ -0,0 +1,170 @@
+//! A local stand-in of the discovery and JWKS endpoints of the OIDC server.
+//!
+//! The keys are HMAC secrets derived from their `kid`, so the tests can sign
+//! the tokens without generating key pairs.
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use poem::{
+    EndpointExt, Route, Server, get, handler,
+    listener::TcpAcceptor,
+    web::{Data, Json},
+};
+use serde_json::{Value, json};
+
+pub const RESOURCE_INDICATOR: &str = "https://api.dbplay.test";
+
+#[derive(Clone, Default)]
+struct State {
+    logto_domain: String,
+    kids: Arc<Mutex<Vec<String>>>,
+    fetches: Arc<AtomicUsize>,
+    hanging: Arc<AtomicBool>,
+}
+
+pub struct MockJwks {
+    state: State,
+    pub logto_domain: String,
+}
+
+impl MockJwks {
+    /// Serve the OIDC server with the keys `kids` on a random local port.
+    pub async fn serve(kids: &[&str]) -> Self {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .expect("failed to bind jwks");
+        let addr = listener.local_addr().expect("no local address");
+
+        let logto_domain = format!("http://{addr}/");
+        let state = State {
+            logto_domain: logto_domain.clone(),
+            ..Default::default()
+        };
+        let app = Route::new()
+            .at("/oidc/.well-known/openid-configuration", get(configuration))
+            .at("/oidc/jwks", get(jwks))
+            .data(state.clone());
+        let acceptor = TcpAcceptor::from_tokio(listener).expect("invalid listener");
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(app));
+
+        let mock = Self {
+            state,
+            logto_domain,
+        };
+        mock.rotate(kids);
+        mock
+    }
+
+    /// Replace the keys in the set.
+    pub fn rotate(&self, kids: &[&str]) {
+        *self.state.kids.lock().unwrap() = kids.iter().map(|kid| kid.to_string()).collect();
+    }
+
+    /// The number of the requests to the JWKS endpoint.
+    pub fn fetches(&self) -> usize {
+        self.state.fetches.load(Ordering::SeqCst)
+    }
+
+    /// Make the JWKS endpoint stop responding.
+    pub fn hang(&self, hanging: bool) {
+        self.state.hanging.store(hanging, Ordering::SeqCst);
+    }
+
+    /// The issuer of the tokens.
+    pub fn issuer(&self) -> String {
+        format!("{}oidc", self.logto_domain)
+    }
+
+    /// Sign a token for `sub` with the key `kid`.
+    pub fn token(&self, kid: Option<&str>, alg: Algorithm, sub: &str, scope: &str) -> String {
+        let exp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 3600;
+        let claims = json!({
+            "iss": self.issuer(),
+            "aud": RESOURCE_INDICATOR,
+            "sub": sub,
+            "scope": scope,
+            "exp": exp,
+        });
+        let header = Header {
+            kid: kid.map(str::to_string),
+            ..Header::new(alg)
+        };
+        let key = EncodingKey::from_secret(&secret(kid.unwrap_or_default()));
+
+        jsonwebtoken::encode(&header, &claims, &key).expect("failed to sign token")
+    }
+}
+
+/// Sign a token for `sub` with `secret` for the shared-secret mode.
+pub fn shared_secret_token(secret: &[u8], alg: Algorithm, sub: &str, scope: &str) -> String {
+    shared_secret_token_with_roles(secret, alg, sub, scope, &[])
+}
+
+/// Sign a token for `sub` granting `roles` with `secret`.
+pub fn shared_secret_token_with_roles(
+    secret: &[u8],
+    alg: Algorithm,
+    sub: &str,
+    scope: &str,
+    roles: &[&str],
+) -> String {
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    let claims = json!({ "sub": sub, "scope": scope, "exp": exp, "roles": roles });
+    let key = EncodingKey::from_secret(secret);
+
+    jsonwebtoken::encode(&Header::new(alg), &claims, &key).expect("failed to sign token")
+}
+
+#[handler]
+fn configuration(state: Data<&State>) -> Json<Value> {
+    Json(json!({
+        "issuer": format!("{}oidc", state.logto_domain),
+        "jwks_uri": format!("{}oidc/jwks", state.logto_domain),
+    }))
+}
+
+#[handler]
+async fn jwks(state: Data<&State>) -> Json<Value> {
+    state.fetches.fetch_add(1, Ordering::SeqCst);
+    if state.hanging.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+    }
+
+    let keys = state
+        .kids
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|kid| {
+            json!({
+                "kty": "oct",
+                "kid": kid,
+                "alg": "HS256",
+                "k": URL_SAFE_NO_PAD.encode(secret(kid)),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Json(json!({ "keys": keys }))
+}
+
+fn secret(kid: &str) -> Vec<u8> {
+    format!("secret-of-{kid}").into_bytes()
+}
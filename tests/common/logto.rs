@@ -0,0 +1,160 @@
+//! A local stand-in of the token endpoint and the user API of the Logto
+//! Management API.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use backend::logto::{LogtoClient, LogtoConfig};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use poem::{
+    EndpointExt, Route, Server, delete, handler,
+    http::{HeaderMap, StatusCode},
+    listener::TcpAcceptor,
+    post,
+    web::{Data, Form, Json, Path},
+};
+use serde_json::{Value, json};
+
+pub const APP_ID: &str = "m2m-app";
+pub const APP_SECRET: &str = "m2m-secret";
+pub const RESOURCE: &str = "https://default.logto.app/api";
+
+#[derive(Clone, Default)]
+struct State {
+    users: Arc<Mutex<Vec<String>>>,
+    token_requests: Arc<AtomicUsize>,
+    failing: Arc<AtomicBool>,
+    hanging: Arc<AtomicBool>,
+}
+
+pub struct MockLogto {
+    state: State,
+    pub endpoint: String,
+}
+
+impl MockLogto {
+    /// Serve the Logto server with the users `users` on a random local port.
+    pub async fn serve(users: &[&str]) -> Self {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .expect("failed to bind logto");
+        let addr = listener.local_addr().expect("no local address");
+
+        let state = State {
+            users: Arc::new(Mutex::new(users.iter().map(|u| u.to_string()).collect())),
+            ..Default::default()
+        };
+        let app = Route::new()
+            .at("/oidc/token", post(token))
+            .at("/api/users/:id", delete(delete_user))
+            .data(state.clone());
+        let acceptor = TcpAcceptor::from_tokio(listener).expect("invalid listener");
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(app));
+
+        Self {
+            state,
+            endpoint: format!("http://{addr}/"),
+        }
+    }
+
+    /// Build a client of this server with `app_secret`.
+    pub fn client(&self, app_secret: &str) -> LogtoClient {
+        LogtoClient::new(self.config(app_secret))
+    }
+
+    /// The configuration of a client of this server with `app_secret`.
+    pub fn config(&self, app_secret: &str) -> LogtoConfig {
+        LogtoConfig {
+            endpoint: self.endpoint.parse().expect("invalid endpoint"),
+            app_id: APP_ID.to_string(),
+            app_secret: app_secret.to_string(),
+            resource: RESOURCE.to_string(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// The users which have not been deleted.
+    pub fn users(&self) -> Vec<String> {
+        self.state.users.lock().unwrap().clone()
+    }
+
+    /// The number of the requests to the token endpoint.
+    pub fn token_requests(&self) -> usize {
+        self.state.token_requests.load(Ordering::SeqCst)
+    }
+
+    /// Make the token endpoint stop responding.
+    pub fn hang(&self, hanging: bool) {
+        self.state.hanging.store(hanging, Ordering::SeqCst);
+    }
+
+    /// Make the user API respond with 500 Internal Server Error.
+    pub fn fail(&self, failing: bool) {
+        self.state.failing.store(failing, Ordering::SeqCst);
+    }
+}
+
+#[handler]
+async fn token(
+    state: Data<&State>,
+    headers: &HeaderMap,
+    Form(form): Form<HashMap<String, String>>,
+) -> Result<Json<Value>, StatusCode> {
+    if state.hanging.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+    }
+
+    let credentials = format!(
+        "Basic {}",
+        STANDARD.encode(format!("{APP_ID}:{APP_SECRET}"))
+    );
+    let authorized = headers
+        .get("Authorization")
+        .is_some_and(|header| header.as_bytes() == credentials.as_bytes());
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    if form.get("grant_type").map(String::as_str) != Some("client_credentials")
+        || form.get("resource").map(String::as_str) != Some(RESOURCE)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let n = state.token_requests.fetch_add(1, Ordering::SeqCst);
+    Ok(Json(json!({
+        "access_token": format!("access-token-{n}"),
+        "expires_in": 3600,
+        "token_type": "Bearer",
+        "scope": "all",
+    })))
+}
+
+#[handler]
+fn delete_user(state: Data<&State>, headers: &HeaderMap, Path(id): Path<String>) -> StatusCode {
+    let authorized = headers
+        .get("Authorization")
+        .and_then(|header| header.to_str().ok())
+        .is_some_and(|header| header.starts_with("Bearer access-token-"));
+    if !authorized {
+        return StatusCode::UNAUTHORIZED;
+    }
+    if state.failing.load(Ordering::SeqCst) {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    let mut users = state.users.lock().unwrap();
+    match users.iter().position(|user| *user == id) {
+        Some(index) => {
+            users.remove(index);
+            StatusCode::NO_CONTENT
+        }
+        None => StatusCode::NOT_FOUND,
+    }
+}
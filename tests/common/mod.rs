@@ -0,0 +1,95 @@
+//! The helpers shared by the GraphQL-level integration tests.
+
+// Each test crate only uses a part of the helpers.
+#![allow(dead_code)]
+
+#[cfg(feature = "dbrunner_server")]
+pub mod dbrunner;
+pub mod jwks;
+pub mod logto;
+
+use async_graphql::{SchemaBuilder, dataloader::DataLoader};
+use backend::{
+    gql::{
+        self,
+        auth::{Auth, AuthBuilder, Scope, SharedSecretProvider},
+        loader::{GroupLoader, QuestionLoader, SchemaLoader},
+        rate_limit::{RateLimiter, RateLimiterConfig},
+        user_cache::UserCache,
+    },
+    logto::LogtoClient,
+    rpc::DbRunnerClient,
+};
+use poem::{EndpointExt, Route, post, test::TestClient};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+/// The secret of the JWTs accepted by [`send`].
+pub const SHARED_SECRET: &[u8] = b"secret";
+
+/// Build the GraphQL schema as `main.rs` does.
+pub fn schema(pool: PgPool, dbrunner: Option<DbRunnerClient>) -> gql::Schema {
+    schema_builder(pool, dbrunner).finish()
+}
+
+/// Build the GraphQL schema as `main.rs` does, leaving the builder open to
+/// replace the data, such as the Logto client.
+pub fn schema_builder(
+    pool: PgPool,
+    dbrunner: Option<DbRunnerClient>,
+) -> SchemaBuilder<gql::Query, gql::Mutation, gql::Subscription> {
+    gql::Schema::build(
+        gql::Query::default(),
+        gql::Mutation::default(),
+        gql::Subscription::default(),
+    )
+    .data(DataLoader::new(SchemaLoader(pool.clone()), tokio::spawn))
+    .data(DataLoader::new(GroupLoader(pool.clone()), tokio::spawn))
+    .data(DataLoader::new(QuestionLoader(pool.clone()), tokio::spawn))
+    .data(pool)
+    .data(dbrunner)
+    .data(RateLimiter::new(RateLimiterConfig::default()))
+    .data(None::<LogtoClient>)
+    .data(UserCache::default())
+}
+
+/// Build a request sent by `sub` with `scopes`.
+pub fn request(
+    query: impl Into<String>,
+    sub: &str,
+    scopes: impl IntoIterator<Item = Scope>,
+) -> async_graphql::Request {
+    request_with_auth(query, Auth::new(sub, scopes))
+}
+
+/// Build a request sent with `auth`.
+pub fn request_with_auth(query: impl Into<String>, auth: Auth) -> async_graphql::Request {
+    async_graphql::Request::new(query).data(auth)
+}
+
+/// Send `query` to the GraphQL endpoint as `main.rs` serves it, with the
+/// bearer `token`. The JWTs are signed with [`SHARED_SECRET`].
+pub async fn send(pool: &PgPool, user_cache: &UserCache, token: &str, query: &str) -> Value {
+    let app = Route::new()
+        .at("/", post(gql::poem::index))
+        .data(
+            schema_builder(pool.clone(), None)
+                .data(user_cache.clone())
+                .finish(),
+        )
+        .data(AuthBuilder::new(SharedSecretProvider::new(SHARED_SECRET)))
+        .data(pool.clone())
+        .data(user_cache.clone());
+
+    TestClient::new(app)
+        .post("/")
+        .header("Authorization", format!("Bearer {token}"))
+        .body_json(&json!({ "query": query }))
+        .send()
+        .await
+        .0
+        .into_body()
+        .into_json()
+        .await
+        .expect("invalid json")
+}
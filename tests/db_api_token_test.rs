@@ -0,0 +1,140 @@
+#![cfg(all(test, feature = "test_database"))]
+
+use std::assert_matches;
+
+use backend::db::{self, ApiTokenCreateParameter};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+async fn create(pool: &PgPool, user_id: &str, token_hash: &[u8]) -> db::ApiToken {
+    db::create_api_token(
+        pool,
+        ApiTokenCreateParameter {
+            user_id,
+            name: "ci",
+            token_hash,
+            scopes: &["read:answer".to_string()],
+            expires_at: None,
+        },
+    )
+    .await
+    .expect("failed to create API token")
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_create_and_list(pool: PgPool) {
+    let first = create(&pool, "usergeneric0", b"hash0").await;
+    let second = create(&pool, "usergeneric0", b"hash1").await;
+    create(&pool, "usergeneric1", b"hash2").await;
+
+    assert_eq!(first.user_id, "usergeneric0");
+    assert_eq!(first.scopes, vec!["read:answer"]);
+    assert_eq!(first.last_used_at, None);
+
+    let tokens = db::list_api_tokens(&pool, "usergeneric0")
+        .await
+        .expect("failed to list API tokens");
+    let ids = tokens.iter().map(|t| t.api_token_id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![second.api_token_id, first.api_token_id]);
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_duplicated_hash(pool: PgPool) {
+    create(&pool, "usergeneric0", b"hash0").await;
+
+    let result = db::create_api_token(
+        &pool,
+        ApiTokenCreateParameter {
+            user_id: "usergeneric1",
+            name: "ci",
+            token_hash: b"hash0",
+            scopes: &[],
+            expires_at: None,
+        },
+    )
+    .await;
+    assert_matches!(result, Err(db::Error::DatabaseError(_)));
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_use(pool: PgPool) {
+    let token = create(&pool, "usergeneric0", b"hash0").await;
+
+    let used = db::use_api_token(&pool, b"hash0")
+        .await
+        .expect("failed to use API token")
+        .expect("token should be valid");
+    assert_eq!(used.api_token_id, token.api_token_id);
+    assert!(used.last_used_at.is_some());
+
+    let unknown = db::use_api_token(&pool, b"unknown")
+        .await
+        .expect("failed to use API token");
+    assert!(unknown.is_none());
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_use_expired(pool: PgPool) {
+    db::create_api_token(
+        &pool,
+        ApiTokenCreateParameter {
+            user_id: "usergeneric0",
+            name: "ci",
+            token_hash: b"hash0",
+            scopes: &[],
+            expires_at: Some(Utc::now() - Duration::hours(1)),
+        },
+    )
+    .await
+    .expect("failed to create API token");
+
+    let used = db::use_api_token(&pool, b"hash0")
+        .await
+        .expect("failed to use API token");
+    assert!(used.is_none());
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_use_of_deleted_user(pool: PgPool) {
+    create(&pool, "usergeneric0", b"hash0").await;
+    db::delete_user(&pool, "usergeneric0")
+        .await
+        .expect("failed to delete user");
+
+    let used = db::use_api_token(&pool, b"hash0")
+        .await
+        .expect("failed to use API token");
+    assert!(used.is_none());
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_revoke(pool: PgPool) {
+    let token = create(&pool, "usergeneric0", b"hash0").await;
+
+    let result = db::revoke_api_token(&pool, "usergeneric1", token.api_token_id).await;
+    assert_matches!(
+        result,
+        Err(db::Error::NotFound {
+            entity: "api_token",
+            ..
+        }),
+        "only the owner can revoke the token"
+    );
+
+    db::revoke_api_token(&pool, "usergeneric0", token.api_token_id)
+        .await
+        .expect("failed to revoke API token");
+
+    let used = db::use_api_token(&pool, b"hash0")
+        .await
+        .expect("failed to use API token");
+    assert!(used.is_none());
+
+    let tokens = db::list_api_tokens(&pool, "usergeneric0")
+        .await
+        .expect("failed to list API tokens");
+    assert!(tokens.is_empty());
+
+    let result = db::revoke_api_token(&pool, "usergeneric0", token.api_token_id).await;
+    assert_matches!(result, Err(db::Error::NotFound { .. }));
+}
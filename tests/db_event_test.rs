@@ -1,6 +1,6 @@
 #![cfg(all(test, feature = "test_database"))]
 
-use backend::db::AttemptStatus;
+use backend::db::{AttemptFilter, AttemptStatus, KeysetCursor};
 use sqlx::PgPool;
 
 #[sqlx::test(fixtures("group", "user", "schema", "question"))]
@@ -51,6 +51,30 @@ async fn test_mark_attempt_event(pool: PgPool) {
     assert_eq!(attempt.status, AttemptStatus::Passed);
 }
 
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_mark_attempt_event_failed(pool: PgPool) {
+    let event_id = backend::db::create_attempt_event(
+        &pool,
+        "usergeneric0",
+        1,
+        "SELECT;",
+        backend::db::AttemptStatus::Pending,
+    )
+    .await
+    .expect("failed to create attempt event");
+
+    backend::db::mark_attempt_event_failed(&pool, event_id, "syntax error")
+        .await
+        .expect("failed to mark attempt event as failed");
+
+    let attempt = sqlx::query!(
+        r#"SELECT status AS "status: AttemptStatus", error FROM dp_attempt_events WHERE attempt_event_id = $1;"#,
+        event_id
+    ).fetch_one(&pool).await.expect("failed to fetch attempt event");
+    assert_eq!(attempt.status, AttemptStatus::Failed);
+    assert_eq!(attempt.error.as_deref(), Some("syntax error"));
+}
+
 #[sqlx::test(fixtures("group", "user", "schema", "question"))]
 async fn test_create_solution_event(pool: PgPool) {
     let event_id = backend::db::create_solution_event(&pool, "usergeneric0", 1)
@@ -67,3 +91,209 @@ async fn test_create_solution_event(pool: PgPool) {
     assert_eq!(solution.user_id, "usergeneric0");
     assert_eq!(solution.question_id, 1);
 }
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_list_solution_events(pool: PgPool) {
+    for question_id in [1, 2, 3] {
+        backend::db::create_solution_event(&pool, "usergeneric0", question_id)
+            .await
+            .expect("failed to create solution event");
+    }
+    backend::db::create_solution_event(&pool, "usergroup1", 1)
+        .await
+        .expect("failed to create solution event");
+
+    let page = backend::db::list_solution_events(&pool, "usergeneric0", KeysetCursor::default())
+        .await
+        .expect("failed to list solution events");
+    assert_eq!(page.items.len(), 3, "only the events of usergeneric0");
+    assert_eq!(page.items[0].question_id, 3, "newest first");
+    assert!(page.items.iter().all(|e| e.user_id == "usergeneric0"));
+    assert!(!page.has_previous_page && !page.has_next_page);
+
+    let page = backend::db::list_solution_events(
+        &pool,
+        "usergeneric0",
+        KeysetCursor {
+            first: Some(1),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list solution events");
+    assert_eq!(page.items.len(), 1);
+    assert!(page.has_next_page);
+
+    let page = backend::db::list_solution_events(
+        &pool,
+        "usergeneric0",
+        KeysetCursor {
+            after: Some(page.items[0].solution_event_id),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list solution events");
+    assert_eq!(
+        page.items.iter().map(|e| e.question_id).collect::<Vec<_>>(),
+        vec![2, 1]
+    );
+    assert!(page.has_previous_page);
+
+    let count = backend::db::count_solution_events(&pool, "usergeneric0")
+        .await
+        .expect("failed to count solution events");
+    assert_eq!(count, 3);
+}
+
+/// Create the attempts of usergeneric0 as `(question_id, status)`, and one
+/// attempt of usergroup1. Returns the event IDs of usergeneric0.
+async fn create_attempts(pool: &PgPool, attempts: &[(i64, AttemptStatus)]) -> Vec<i64> {
+    let mut ids = Vec::new();
+    for &(question_id, status) in attempts {
+        let id = backend::db::create_attempt_event(
+            pool,
+            "usergeneric0",
+            question_id,
+            "SELECT 1;",
+            status,
+        )
+        .await
+        .expect("failed to create attempt event");
+        ids.push(id);
+    }
+
+    backend::db::create_attempt_event(pool, "usergroup1", 1, "SELECT 2;", AttemptStatus::Passed)
+        .await
+        .expect("failed to create attempt event");
+
+    ids
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_list_attempt_events(pool: PgPool) {
+    let ids = create_attempts(
+        &pool,
+        &[
+            (1, AttemptStatus::Failed),
+            (1, AttemptStatus::Passed),
+            (2, AttemptStatus::Pending),
+        ],
+    )
+    .await;
+
+    let page = backend::db::list_attempt_events(
+        &pool,
+        "usergeneric0",
+        AttemptFilter::default(),
+        KeysetCursor::default(),
+    )
+    .await
+    .expect("failed to list attempt events");
+    let listed: Vec<_> = page.items.iter().map(|e| e.attempt_event_id).collect();
+    assert_eq!(listed, [ids[2], ids[1], ids[0]], "newest first");
+    assert!(page.items.iter().all(|e| e.user_id == "usergeneric0"));
+    assert_eq!(page.items[0].query, "SELECT 1;");
+    assert!(!page.has_previous_page);
+    assert!(!page.has_next_page);
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_list_attempt_events_filter(pool: PgPool) {
+    let ids = create_attempts(
+        &pool,
+        &[
+            (1, AttemptStatus::Failed),
+            (1, AttemptStatus::Passed),
+            (2, AttemptStatus::Passed),
+        ],
+    )
+    .await;
+
+    let by_question = AttemptFilter {
+        question_id: Some(1),
+        status: None,
+    };
+    let page = backend::db::list_attempt_events(
+        &pool,
+        "usergeneric0",
+        by_question,
+        KeysetCursor::default(),
+    )
+    .await
+    .expect("failed to list attempt events");
+    let listed: Vec<_> = page.items.iter().map(|e| e.attempt_event_id).collect();
+    assert_eq!(listed, [ids[1], ids[0]]);
+
+    let by_status = AttemptFilter {
+        question_id: None,
+        status: Some(AttemptStatus::Passed),
+    };
+    let page =
+        backend::db::list_attempt_events(&pool, "usergeneric0", by_status, KeysetCursor::default())
+            .await
+            .expect("failed to list attempt events");
+    let listed: Vec<_> = page.items.iter().map(|e| e.attempt_event_id).collect();
+    assert_eq!(listed, [ids[2], ids[1]]);
+
+    let both = AttemptFilter {
+        question_id: Some(1),
+        status: Some(AttemptStatus::Passed),
+    };
+    let count = backend::db::count_attempt_events(&pool, "usergeneric0", both)
+        .await
+        .expect("failed to count attempt events");
+    assert_eq!(count, 1);
+
+    let count = backend::db::count_attempt_events(&pool, "usergeneric0", AttemptFilter::default())
+        .await
+        .expect("failed to count attempt events");
+    assert_eq!(count, 3);
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_list_attempt_events_pagination(pool: PgPool) {
+    let ids = create_attempts(
+        &pool,
+        &[
+            (1, AttemptStatus::Failed),
+            (2, AttemptStatus::Failed),
+            (3, AttemptStatus::Failed),
+            (4, AttemptStatus::Failed),
+            (5, AttemptStatus::Failed),
+        ],
+    )
+    .await;
+
+    let page = backend::db::list_attempt_events(
+        &pool,
+        "usergeneric0",
+        AttemptFilter::default(),
+        KeysetCursor {
+            after: Some(ids[4]),
+            first: Some(2),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list attempt events");
+    let listed: Vec<_> = page.items.iter().map(|e| e.attempt_event_id).collect();
+    assert_eq!(listed, [ids[3], ids[2]], "older events after the cursor");
+    assert!(page.has_next_page);
+
+    let page = backend::db::list_attempt_events(
+        &pool,
+        "usergeneric0",
+        AttemptFilter::default(),
+        KeysetCursor {
+            before: Some(ids[0]),
+            last: Some(2),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list attempt events");
+    let listed: Vec<_> = page.items.iter().map(|e| e.attempt_event_id).collect();
+    assert_eq!(listed, [ids[2], ids[1]], "newer events before the cursor");
+    assert!(page.has_previous_page);
+}
@@ -0,0 +1,206 @@
+#![cfg(all(test, feature = "test_database"))]
+
+use backend::db::{AttemptStatus, Difficulty, ProgressBreakdown};
+use sqlx::PgPool;
+
+/// Record the attempts of usergeneric0 as `(question_id, status)` in order.
+async fn create_attempts(pool: &PgPool, attempts: &[(i64, AttemptStatus)]) {
+    for &(question_id, status) in attempts {
+        backend::db::create_attempt_event(pool, "usergeneric0", question_id, "SELECT 1;", status)
+            .await
+            .expect("failed to create attempt event");
+    }
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_get_progress_breakdown(pool: PgPool) {
+    create_attempts(
+        &pool,
+        &[
+            (1, AttemptStatus::Failed),
+            (1, AttemptStatus::Passed),
+            (3, AttemptStatus::Failed),
+            (9, AttemptStatus::Passed),
+            (20, AttemptStatus::Passed),
+        ],
+    )
+    .await;
+
+    let breakdown = backend::db::get_progress_breakdown(&pool, "usergeneric0")
+        .await
+        .expect("failed to get progress");
+
+    let solved: i64 = breakdown.iter().map(|b| b.solved).sum();
+    let attempted: i64 = breakdown.iter().map(|b| b.attempted).sum();
+    let untouched: i64 = breakdown.iter().map(|b| b.untouched).sum();
+    assert_eq!(solved, 2, "deleted questions are not counted");
+    assert_eq!(attempted, 1, "solved questions are not attempted");
+    assert_eq!(untouched, 16);
+
+    assert!(breakdown.contains(&ProgressBreakdown {
+        difficulty: Difficulty::Easy,
+        question_type: "條件查詢".to_string(),
+        solved: 1,
+        attempted: 0,
+        untouched: 1,
+    }));
+    assert!(breakdown.contains(&ProgressBreakdown {
+        difficulty: Difficulty::Medium,
+        question_type: "條件查詢".to_string(),
+        solved: 0,
+        attempted: 1,
+        untouched: 0,
+    }));
+    assert!(breakdown.contains(&ProgressBreakdown {
+        difficulty: Difficulty::Hard,
+        question_type: "子查詢應用".to_string(),
+        solved: 1,
+        attempted: 0,
+        untouched: 0,
+    }));
+    assert_eq!(breakdown[0].difficulty, Difficulty::Easy, "easiest first");
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_get_progress_breakdown_untouched(pool: PgPool) {
+    let breakdown = backend::db::get_progress_breakdown(&pool, "usergeneric1")
+        .await
+        .expect("failed to get progress");
+
+    assert!(breakdown.iter().all(|b| b.solved == 0 && b.attempted == 0));
+    assert_eq!(breakdown.iter().map(|b| b.untouched).sum::<i64>(), 19);
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_list_first_solves(pool: PgPool) {
+    create_attempts(
+        &pool,
+        &[
+            (9, AttemptStatus::Passed),
+            (1, AttemptStatus::Failed),
+            (1, AttemptStatus::Passed),
+            (9, AttemptStatus::Passed),
+            (3, AttemptStatus::Failed),
+        ],
+    )
+    .await;
+
+    let solves = backend::db::list_first_solves(&pool, "usergeneric0")
+        .await
+        .expect("failed to list first solves");
+    let question_ids: Vec<_> = solves.iter().map(|s| s.question_id).collect();
+    assert_eq!(question_ids, [9, 1], "earliest first, unsolved omitted");
+    assert!(solves[0].solved_at <= solves[1].solved_at);
+}
+
+/// Record an attempt of `user_id` created `minutes_ago` minutes ago.
+async fn create_attempt_at(
+    pool: &PgPool,
+    user_id: &str,
+    question_id: i64,
+    status: AttemptStatus,
+    minutes_ago: i64,
+) {
+    sqlx::query(
+        r#"
+        INSERT INTO dp_attempt_events (user_id, question_id, query, status, created_at)
+        VALUES ($1, $2, 'SELECT 1;', $3, now() - make_interval(mins => $4::int))
+        "#,
+    )
+    .bind(user_id)
+    .bind(question_id)
+    .bind(status)
+    .bind(minutes_ago)
+    .execute(pool)
+    .await
+    .expect("failed to create attempt event");
+}
+
+/// Put usergeneric0 and usergeneric1 into group 1 along with usergroup1, and
+/// record their attempts:
+///
+/// - usergroup1 passed a hard question in 10 minutes, 30 days ago, and passed
+///   it again two hours ago.
+/// - usergeneric0 passed an easy question at once and a medium question in
+///   5 minutes, an hour ago.
+/// - usergeneric1 has not attempted anything.
+async fn prepare_leaderboard(pool: &PgPool) {
+    for user_id in ["usergeneric0", "usergeneric1"] {
+        backend::db::set_user_group(pool, user_id, Some(1))
+            .await
+            .expect("failed to set user group");
+    }
+
+    let days_30 = 30 * 24 * 60;
+    create_attempt_at(pool, "usergroup1", 9, AttemptStatus::Failed, days_30).await;
+    create_attempt_at(pool, "usergroup1", 9, AttemptStatus::Passed, days_30 - 10).await;
+    create_attempt_at(pool, "usergroup1", 9, AttemptStatus::Passed, days_30 - 20).await;
+    create_attempt_at(pool, "usergroup1", 9, AttemptStatus::Passed, 120).await;
+
+    create_attempt_at(pool, "usergeneric0", 1, AttemptStatus::Passed, 60).await;
+    create_attempt_at(pool, "usergeneric0", 3, AttemptStatus::Failed, 60).await;
+    create_attempt_at(pool, "usergeneric0", 3, AttemptStatus::Passed, 55).await;
+
+    // Attempts outside the group are not counted.
+    create_attempt_at(pool, "usergroup2", 19, AttemptStatus::Passed, 60).await;
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_get_group_leaderboard(pool: PgPool) {
+    prepare_leaderboard(&pool).await;
+
+    let entries = backend::db::get_group_leaderboard(&pool, 1, None)
+        .await
+        .expect("failed to get leaderboard");
+
+    let ranking: Vec<_> = entries
+        .iter()
+        .map(|e| (e.user_id.as_str(), e.rank, e.passed, e.score))
+        .collect();
+    assert_eq!(
+        ranking,
+        [
+            ("usergeneric0", 1, 2, 3),
+            ("usergroup1", 2, 1, 3),
+            ("usergeneric1", 3, 0, 0),
+        ],
+        "ties of score are broken by time to pass"
+    );
+    assert_eq!(entries[0].time_to_pass, Some(5 * 60));
+    assert_eq!(entries[1].time_to_pass, Some(10 * 60));
+    assert_eq!(entries[2].time_to_pass, None);
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_get_group_leaderboard_window(pool: PgPool) {
+    prepare_leaderboard(&pool).await;
+
+    let since = chrono::Utc::now() - chrono::Duration::days(7);
+    let entries = backend::db::get_group_leaderboard(&pool, 1, Some(since))
+        .await
+        .expect("failed to get leaderboard");
+
+    let ranking: Vec<_> = entries
+        .iter()
+        .map(|e| (e.user_id.as_str(), e.rank, e.score))
+        .collect();
+    assert_eq!(
+        ranking,
+        [
+            ("usergeneric0", 1, 3),
+            ("usergeneric1", 2, 0),
+            ("usergroup1", 2, 0),
+        ],
+        "the questions first passed before the window are not counted"
+    );
+    assert_eq!(entries[0].time_to_pass, Some(5 * 60));
+    assert_eq!(entries[2].time_to_pass, None);
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_get_group_leaderboard_empty(pool: PgPool) {
+    let entries = backend::db::get_group_leaderboard(&pool, 4, None)
+        .await
+        .expect("failed to get leaderboard");
+    assert!(entries.is_empty());
+}
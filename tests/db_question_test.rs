@@ -1,98 +1,340 @@
 #![cfg(all(test, feature = "test_database"))]
-#![feature(assert_matches)]
 
-use std::assert_matches::assert_matches;
+use std::assert_matches;
 
-use backend::db::{self, Cursor};
+use backend::db::{
+    self, Difficulty, KeysetCursor, QuestionCreateParameter, QuestionFilter, QuestionOrder,
+    QuestionUpdateParameter,
+};
 use sqlx::PgPool;
 
 #[sqlx::test(fixtures("schema", "question"))]
 async fn test_list_questions_default_cursor(pool: PgPool) {
-    let questions = backend::db::list_questions(&pool, Cursor::default())
-        .await
-        .expect("failed to list questions");
+    let page = backend::db::list_questions(
+        &pool,
+        &QuestionFilter::default(),
+        QuestionOrder::Id,
+        KeysetCursor::default(),
+    )
+    .await
+    .expect("failed to list questions");
 
-    assert_eq!(questions.len(), 10, "default offset=0, limit=10");
-    assert_eq!(questions[0].title, "Find a product in the shop");
+    assert_eq!(page.items.len(), 10, "default first=10");
+    assert_eq!(page.items[0].title, "Find a product in the shop");
+    assert!(!page.has_previous_page);
+    assert!(page.has_next_page);
 }
 
 #[sqlx::test(fixtures("schema", "question"))]
-async fn test_list_questions_offset_1_limit_5(pool: PgPool) {
-    let questions = backend::db::list_questions(
+async fn test_list_questions_after_1_first_5(pool: PgPool) {
+    let page = backend::db::list_questions(
         &pool,
-        Cursor {
-            offset: Some(1),
-            limit: Some(5),
+        &QuestionFilter::default(),
+        QuestionOrder::Id,
+        KeysetCursor {
+            after: Some(1),
+            first: Some(5),
+            ..Default::default()
         },
     )
     .await
     .expect("failed to list questions");
 
-    assert_eq!(questions.len(), 5, "offset=1, limit=5");
-    assert_eq!(questions[0].title, "List all customers");
+    assert_eq!(page.items.len(), 5, "after=1, first=5");
+    assert_eq!(page.items[0].title, "List all customers");
+    assert!(page.has_previous_page);
+    assert!(page.has_next_page);
 }
 
 #[sqlx::test(fixtures("schema", "question"))]
-async fn test_list_questions_offset_5_limit_5(pool: PgPool) {
-    let questions = backend::db::list_questions(
+async fn test_list_questions_after_5_first_5(pool: PgPool) {
+    let page = backend::db::list_questions(
         &pool,
-        Cursor {
-            offset: Some(5),
-            limit: Some(5),
+        &QuestionFilter::default(),
+        QuestionOrder::Id,
+        KeysetCursor {
+            after: Some(5),
+            first: Some(5),
+            ..Default::default()
         },
     )
     .await
     .expect("failed to list questions");
 
-    assert_eq!(questions.len(), 5, "offset=5, limit=5");
-    assert_eq!(questions[0].title, "Find a book by title");
+    assert_eq!(page.items.len(), 5, "after=5, first=5");
+    assert_eq!(page.items[0].title, "Find a book by title");
 }
 
 #[sqlx::test(fixtures("schema", "question"))]
-async fn test_list_questions_offset_0_limit_5(pool: PgPool) {
-    let questions = backend::db::list_questions(
+async fn test_list_questions_last_page(pool: PgPool) {
+    let page = backend::db::list_questions(
         &pool,
-        Cursor {
-            offset: Some(0),
-            limit: Some(5),
+        &QuestionFilter::default(),
+        QuestionOrder::Id,
+        KeysetCursor {
+            after: Some(15),
+            first: Some(10),
+            ..Default::default()
         },
     )
     .await
     .expect("failed to list questions");
 
-    assert_eq!(questions.len(), 5, "offset=0, limit=5");
-    assert_eq!(questions[0].title, "Find a product in the shop");
+    assert_eq!(page.items.len(), 4, "deleted question should be skipped");
+    assert_eq!(page.items[3].question_id, 19);
+    assert!(!page.has_next_page);
 }
 
 #[sqlx::test(fixtures("schema", "question"))]
-async fn test_list_questions_limit_0(pool: PgPool) {
-    let questions = backend::db::list_questions(
+async fn test_list_questions_before_6_last_2(pool: PgPool) {
+    let page = backend::db::list_questions(
         &pool,
-        Cursor {
-            limit: Some(0),
+        &QuestionFilter::default(),
+        QuestionOrder::Id,
+        KeysetCursor {
+            before: Some(6),
+            last: Some(2),
             ..Default::default()
         },
     )
     .await
     .expect("failed to list questions");
 
-    assert_eq!(questions.len(), 0, "limit=0 should returns nothing");
+    let ids = page.items.iter().map(|q| q.question_id).collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        vec![4, 5],
+        "before=6, last=2 should be in ascending order"
+    );
+    assert!(page.has_previous_page);
+    assert!(page.has_next_page);
 }
 
 #[sqlx::test(fixtures("schema", "question"))]
-async fn test_list_questions_limit_none(pool: PgPool) {
-    let questions = backend::db::list_questions(
+async fn test_list_questions_first_0(pool: PgPool) {
+    let page = backend::db::list_questions(
         &pool,
-        Cursor {
-            limit: None,
+        &QuestionFilter::default(),
+        QuestionOrder::Id,
+        KeysetCursor {
+            first: Some(0),
             ..Default::default()
         },
     )
     .await
     .expect("failed to list questions");
 
-    assert_eq!(questions.len(), 10, "limit=None should returns 10");
-    assert_eq!(questions[0].title, "Find a product in the shop");
+    assert_eq!(page.items.len(), 0, "first=0 should returns nothing");
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_list_questions_stable_after_insert(pool: PgPool) {
+    let cursor = KeysetCursor {
+        after: Some(5),
+        first: Some(3),
+        ..Default::default()
+    };
+    let before =
+        backend::db::list_questions(&pool, &QuestionFilter::default(), QuestionOrder::Id, cursor)
+            .await
+            .expect("failed to list questions");
+
+    sqlx::query!(
+        r#"INSERT INTO dp_questions (schema_id, type, difficulty, title, answer)
+        VALUES ('shop', 'test', 'easy', 'New question', 'SELECT 1;');"#
+    )
+    .execute(&pool)
+    .await
+    .expect("failed to insert question");
+    sqlx::query!(r#"UPDATE dp_questions SET deleted_at = now() WHERE question_id = 2;"#)
+        .execute(&pool)
+        .await
+        .expect("failed to delete question");
+
+    let after =
+        backend::db::list_questions(&pool, &QuestionFilter::default(), QuestionOrder::Id, cursor)
+            .await
+            .expect("failed to list questions");
+
+    assert_eq!(before.items, after.items);
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_count_questions(pool: PgPool) {
+    let count = backend::db::count_questions(&pool, &QuestionFilter::default())
+        .await
+        .expect("failed to count questions");
+
+    assert_eq!(count, 19, "deleted question should not be counted");
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_list_questions_filter(pool: PgPool) {
+    let filter = QuestionFilter {
+        difficulty: Some(Difficulty::Easy),
+        schema_id: Some("library".to_string()),
+        ..Default::default()
+    };
+    let page =
+        backend::db::list_questions(&pool, &filter, QuestionOrder::Id, KeysetCursor::default())
+            .await
+            .expect("failed to list questions");
+
+    let titles = page
+        .items
+        .iter()
+        .map(|q| q.title.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(titles, vec!["List all books", "List all members"]);
+
+    let count = backend::db::count_questions(&pool, &filter)
+        .await
+        .expect("failed to count questions");
+    assert_eq!(count, 2);
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_list_questions_filter_type(pool: PgPool) {
+    let filter = QuestionFilter {
+        question_type: Some("聯集應用".to_string()),
+        ..Default::default()
+    };
+    let page =
+        backend::db::list_questions(&pool, &filter, QuestionOrder::Id, KeysetCursor::default())
+            .await
+            .expect("failed to list questions");
+
+    assert_eq!(page.items.len(), 3);
+    assert!(page.items.iter().all(|q| q.question_type == "聯集應用"));
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_list_questions_search(pool: PgPool) {
+    let filter = QuestionFilter {
+        search: Some("laptop".to_string()),
+        ..Default::default()
+    };
+    let page =
+        backend::db::list_questions(&pool, &filter, QuestionOrder::Id, KeysetCursor::default())
+            .await
+            .expect("failed to list questions");
+
+    let ids = page.items.iter().map(|q| q.question_id).collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        vec![1],
+        "search is case-insensitive and skips deleted questions"
+    );
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_list_questions_search_escaped(pool: PgPool) {
+    let filter = QuestionFilter {
+        search: Some("%".to_string()),
+        ..Default::default()
+    };
+    let page =
+        backend::db::list_questions(&pool, &filter, QuestionOrder::Id, KeysetCursor::default())
+            .await
+            .expect("failed to list questions");
+
+    assert_eq!(page.items.len(), 0, "% should be matched literally");
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_list_questions_order_newest(pool: PgPool) {
+    let page = backend::db::list_questions(
+        &pool,
+        &QuestionFilter::default(),
+        QuestionOrder::Newest,
+        KeysetCursor {
+            first: Some(2),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list questions");
+
+    let ids = page.items.iter().map(|q| q.question_id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![19, 18], "ties are broken by the newest ID");
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_list_questions_order_difficulty_paginated(pool: PgPool) {
+    let first_page = backend::db::list_questions(
+        &pool,
+        &QuestionFilter::default(),
+        QuestionOrder::Difficulty,
+        KeysetCursor {
+            first: Some(8),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list questions");
+    let last = first_page.items.last().expect("page should not be empty");
+
+    let second_page = backend::db::list_questions(
+        &pool,
+        &QuestionFilter::default(),
+        QuestionOrder::Difficulty,
+        KeysetCursor {
+            after: Some(last.question_id),
+            first: Some(8),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list questions");
+
+    let difficulties = first_page
+        .items
+        .iter()
+        .chain(second_page.items.iter())
+        .map(|q| q.difficulty)
+        .collect::<Vec<_>>();
+    assert_eq!(difficulties[..7], [Difficulty::Easy; 7]);
+    assert_eq!(difficulties[7..15], [Difficulty::Medium; 8]);
+    assert_eq!(difficulties[15], Difficulty::Hard);
+
+    let previous_page = backend::db::list_questions(
+        &pool,
+        &QuestionFilter::default(),
+        QuestionOrder::Difficulty,
+        KeysetCursor {
+            before: Some(second_page.items[0].question_id),
+            last: Some(8),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list questions");
+    assert_eq!(previous_page.items, first_page.items);
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_list_questions_order_title(pool: PgPool) {
+    let page = backend::db::list_questions(
+        &pool,
+        &QuestionFilter::default(),
+        QuestionOrder::Title,
+        KeysetCursor {
+            first: Some(2),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list questions");
+
+    let titles = page
+        .items
+        .iter()
+        .map(|q| q.title.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        titles,
+        vec!["Calculate total sales", "Find a book by title"]
+    );
 }
 
 #[sqlx::test(fixtures("schema", "question"))]
@@ -179,6 +421,21 @@ async fn test_get_question_solution_not_found(pool: PgPool) {
     );
 }
 
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_get_questions(pool: PgPool) {
+    let questions = backend::db::get_questions(&pool, &[3, 1, 20, 123456])
+        .await
+        .expect("failed to get questions");
+
+    let mut question_ids: Vec<_> = questions.iter().map(|q| q.question_id).collect();
+    question_ids.sort();
+    assert_eq!(
+        question_ids,
+        [1, 3],
+        "missing and deleted questions are omitted"
+    );
+}
+
 #[sqlx::test(fixtures("schema", "question"))]
 async fn test_get_question_deleted(pool: PgPool) {
     let question = backend::db::get_question(&pool, 20).await;
@@ -236,3 +493,216 @@ async fn test_get_question_schema_initial_sql_not_found(pool: PgPool) {
         "question schema should not be found"
     );
 }
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_create_question(pool: PgPool) {
+    let question_id = backend::db::create_question(
+        &pool,
+        QuestionCreateParameter {
+            schema_id: Some("shop"),
+            question_type: "條件查詢",
+            difficulty: Difficulty::Hard,
+            title: "New question",
+            description: None,
+            answer: "SELECT 1;",
+            solution_video: None,
+        },
+    )
+    .await
+    .expect("failed to create question");
+
+    let question = backend::db::get_question(&pool, question_id)
+        .await
+        .expect("failed to get question");
+    assert_eq!(question.title, "New question");
+    assert_eq!(question.difficulty, Difficulty::Hard);
+    assert_eq!(question.description, "");
+
+    let answer = backend::db::get_question_answer(&pool, question_id)
+        .await
+        .expect("failed to get question answer");
+    assert_eq!(answer, "SELECT 1;");
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_create_question_schema_not_found(pool: PgPool) {
+    let result = backend::db::create_question(
+        &pool,
+        QuestionCreateParameter {
+            schema_id: Some("notfound"),
+            question_type: "條件查詢",
+            difficulty: Difficulty::Easy,
+            title: "New question",
+            description: None,
+            answer: "SELECT 1;",
+            solution_video: None,
+        },
+    )
+    .await;
+
+    assert_matches!(
+        result,
+        Err(db::Error::NotFound {
+            entity: "schema",
+            id,
+        }) if id == "notfound"
+    );
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_update_question(pool: PgPool) {
+    backend::db::update_question(
+        &pool,
+        1,
+        QuestionUpdateParameter {
+            title: Some("Updated title"),
+            solution_video: Some(None),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to update question");
+
+    let question = backend::db::get_question(&pool, 1)
+        .await
+        .expect("failed to get question");
+    assert_eq!(question.title, "Updated title");
+    assert_eq!(question.schema_id.as_deref(), Some("shop"), "unchanged");
+    assert_eq!(question.difficulty, Difficulty::Easy, "unchanged");
+    assert!(
+        question.updated_at > question.created_at,
+        "updated_at should be bumped by moddatetime"
+    );
+
+    let solution = backend::db::get_question_solution(&pool, 1)
+        .await
+        .expect("failed to get question solution");
+    assert_eq!(solution, None);
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_create_update_question_schema_deleted(pool: PgPool) {
+    let result = backend::db::create_question(
+        &pool,
+        QuestionCreateParameter {
+            schema_id: Some("deleted_schema"),
+            question_type: "條件查詢",
+            difficulty: Difficulty::Easy,
+            title: "New question",
+            description: None,
+            answer: "SELECT 1;",
+            solution_video: None,
+        },
+    )
+    .await;
+    assert_matches!(result, Err(db::Error::NotFound {
+        entity: "schema",
+        id,
+    }) if id == "deleted_schema");
+
+    let result = backend::db::update_question(
+        &pool,
+        1,
+        QuestionUpdateParameter {
+            schema_id: Some(Some("deleted_schema")),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_matches!(result, Err(db::Error::NotFound {
+        entity: "schema",
+        id,
+    }) if id == "deleted_schema");
+
+    let question = backend::db::get_question(&pool, 1)
+        .await
+        .expect("failed to get question");
+    assert_eq!(question.schema_id.as_deref(), Some("shop"), "unchanged");
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_update_question_deleted(pool: PgPool) {
+    let result = backend::db::update_question(
+        &pool,
+        20,
+        QuestionUpdateParameter {
+            title: Some("Updated title"),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_matches!(result, Err(db::Error::NotFound {
+        entity: "question",
+        id,
+    }) if id == "20");
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_delete_and_restore_question(pool: PgPool) {
+    backend::db::delete_question(&pool, 1)
+        .await
+        .expect("failed to delete question");
+    assert_matches!(
+        backend::db::get_question(&pool, 1).await,
+        Err(db::Error::NotFound { .. })
+    );
+    assert_matches!(
+        backend::db::delete_question(&pool, 1).await,
+        Err(db::Error::NotFound { .. }),
+        "deleted question cannot be deleted again"
+    );
+
+    backend::db::restore_question(&pool, 1)
+        .await
+        .expect("failed to restore question");
+    backend::db::get_question(&pool, 1)
+        .await
+        .expect("question should be restored");
+    assert_matches!(
+        backend::db::restore_question(&pool, 1).await,
+        Err(db::Error::NotFound { .. }),
+        "question not deleted cannot be restored"
+    );
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_restore_question_schema_deleted(pool: PgPool) {
+    sqlx::query("UPDATE dp_schemas SET deleted_at = now() WHERE schema_id = 'shop'")
+        .execute(&pool)
+        .await
+        .expect("failed to delete schema");
+
+    assert_matches!(
+        backend::db::restore_question(&pool, 20).await,
+        Err(db::Error::NotFound {
+            entity: "schema",
+            id,
+        }) if id == "shop",
+        "the schema should be restored first"
+    );
+    assert_matches!(
+        backend::db::get_question_schema_initial_sql(&pool, 1).await,
+        Err(db::Error::NotFound { .. }),
+        "the question with a deleted schema cannot be executed"
+    );
+
+    backend::db::restore_schema(&pool, "shop")
+        .await
+        .expect("failed to restore schema");
+    backend::db::restore_question(&pool, 20)
+        .await
+        .expect("failed to restore question");
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_delete_and_restore_negative_question_id(pool: PgPool) {
+    assert_matches!(
+        backend::db::delete_question(&pool, -1).await,
+        Err(db::Error::NotPositiveID)
+    );
+    assert_matches!(
+        backend::db::restore_question(&pool, -1).await,
+        Err(db::Error::NotPositiveID)
+    );
+}
@@ -1,5 +1,9 @@
 #![cfg(all(test, feature = "test_database"))]
 
+use std::assert_matches;
+
+use backend::db::{self, KeysetCursor, SchemaCreateParameter, SchemaUpdateParameter};
+
 #[sqlx::test(fixtures("schema"))]
 async fn test_get_schema(pool: sqlx::PgPool) {
     let schema = backend::db::get_schema(&pool, "shop")
@@ -13,6 +17,22 @@ async fn test_get_schema(pool: sqlx::PgPool) {
     println!("{schema:?}");
 }
 
+#[sqlx::test(fixtures("schema"))]
+async fn test_get_schemas(pool: sqlx::PgPool) {
+    let ids = ["shop", "library", "deleted_schema", "unknown"].map(String::from);
+    let schemas = backend::db::get_schemas(&pool, &ids)
+        .await
+        .expect("failed to get schemas");
+
+    let mut schema_ids: Vec<_> = schemas.iter().map(|s| s.schema_id.as_str()).collect();
+    schema_ids.sort();
+    assert_eq!(
+        schema_ids,
+        ["library", "shop"],
+        "missing and deleted schemas are omitted"
+    );
+}
+
 #[sqlx::test(fixtures("schema"))]
 async fn test_get_schema_initial_sql(pool: sqlx::PgPool) {
     let initial_sql = backend::db::get_schema_initial_sql(&pool, "shop")
@@ -26,3 +46,168 @@ async fn test_get_schema_initial_sql(pool: sqlx::PgPool) {
 
     println!("{initial_sql}");
 }
+
+#[sqlx::test(fixtures("schema"))]
+async fn test_list_schemas(pool: sqlx::PgPool) {
+    let page = backend::db::list_schemas(&pool, KeysetCursor::default())
+        .await
+        .expect("failed to list schemas");
+
+    let ids = page
+        .items
+        .iter()
+        .map(|s| s.schema_id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        vec!["library", "school", "shop"],
+        "deleted schema should be skipped"
+    );
+    assert!(!page.has_next_page);
+
+    let page = backend::db::list_schemas(
+        &pool,
+        KeysetCursor {
+            after: Some("library".to_string()),
+            first: Some(1),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to list schemas");
+
+    assert_eq!(page.items[0].schema_id, "school");
+    assert!(page.has_previous_page);
+    assert!(page.has_next_page);
+
+    let count = backend::db::count_schemas(&pool)
+        .await
+        .expect("failed to count schemas");
+    assert_eq!(count, 3);
+}
+
+#[sqlx::test(fixtures("schema"))]
+async fn test_create_schema(pool: sqlx::PgPool) {
+    backend::db::create_schema(
+        &pool,
+        SchemaCreateParameter {
+            schema_id: "bank",
+            picture: Some("https://example.com/bank.png"),
+            description: None,
+            initial_sql: "CREATE TABLE accounts (id INT);",
+        },
+    )
+    .await
+    .expect("failed to create schema");
+
+    let schema = backend::db::get_schema(&pool, "bank")
+        .await
+        .expect("failed to get schema");
+    assert_eq!(
+        schema.picture.as_deref(),
+        Some("https://example.com/bank.png")
+    );
+    assert_eq!(schema.description, "");
+}
+
+#[sqlx::test(fixtures("schema"))]
+async fn test_create_schema_exists(pool: sqlx::PgPool) {
+    let result = backend::db::create_schema(
+        &pool,
+        SchemaCreateParameter {
+            schema_id: "shop",
+            picture: None,
+            description: None,
+            initial_sql: "",
+        },
+    )
+    .await;
+
+    assert_matches!(
+        result,
+        Err(db::Error::AlreadyExists {
+            entity: "schema",
+            id,
+        }) if id == "shop"
+    );
+}
+
+#[sqlx::test(fixtures("schema"))]
+async fn test_update_schema(pool: sqlx::PgPool) {
+    backend::db::update_schema(
+        &pool,
+        "shop",
+        SchemaUpdateParameter {
+            description: Some("A new description"),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("failed to update schema");
+
+    let schema = backend::db::get_schema(&pool, "shop")
+        .await
+        .expect("failed to get schema");
+    assert_eq!(schema.description, "A new description");
+    assert!(schema.updated_at > schema.created_at);
+
+    let initial_sql = backend::db::get_schema_initial_sql(&pool, "shop")
+        .await
+        .expect("failed to get schema initial sql");
+    assert!(initial_sql.contains("CREATE TABLE products"), "unchanged");
+}
+
+#[sqlx::test(fixtures("schema"))]
+async fn test_delete_schema(pool: sqlx::PgPool) {
+    backend::db::delete_schema(&pool, "shop")
+        .await
+        .expect("failed to delete schema");
+
+    assert_matches!(
+        backend::db::get_schema(&pool, "shop").await,
+        Err(db::Error::NotFound {
+            entity: "schema",
+            ..
+        })
+    );
+    assert_matches!(
+        backend::db::delete_schema(&pool, "deleted_schema").await,
+        Err(db::Error::NotFound {
+            entity: "schema",
+            ..
+        })
+    );
+}
+
+#[sqlx::test(fixtures("schema", "question"))]
+async fn test_delete_schema_in_use(pool: sqlx::PgPool) {
+    assert_matches!(
+        backend::db::delete_schema(&pool, "shop").await,
+        Err(db::Error::InUse {
+            entity: "schema",
+            id,
+        }) if id == "shop"
+    );
+    backend::db::get_schema(&pool, "shop")
+        .await
+        .expect("schema in use should not be deleted");
+}
+
+#[sqlx::test(fixtures("schema"))]
+async fn test_restore_schema(pool: sqlx::PgPool) {
+    backend::db::restore_schema(&pool, "deleted_schema")
+        .await
+        .expect("failed to restore schema");
+    backend::db::get_schema(&pool, "deleted_schema")
+        .await
+        .expect("schema should be restored");
+
+    assert_matches!(
+        backend::db::restore_schema(&pool, "shop").await,
+        Err(db::Error::NotFound {
+            entity: "deleted schema",
+            ..
+        }),
+        "schema not deleted cannot be restored"
+    );
+}
@@ -1,8 +1,7 @@
 #![cfg(all(test, feature = "test_database"))]
-#![feature(assert_matches)]
 
 mod test_get_or_initialize_user {
-    use std::assert_matches::assert_matches;
+    use std::assert_matches;
 
     use sqlx::PgPool;
 
@@ -47,7 +46,7 @@ mod test_get_or_initialize_user {
 }
 
 mod test_delete_user {
-    use std::assert_matches::assert_matches;
+    use std::assert_matches;
 
     use sqlx::PgPool;
 
@@ -96,6 +95,7 @@ mod test_create_group {
             GroupCreateParameter {
                 name: "group0",
                 description: None,
+                owner_id: None,
             },
         )
         .await
@@ -119,6 +119,7 @@ mod test_create_group {
             GroupCreateParameter {
                 name: "group1",
                 description: Some("description1"),
+                owner_id: None,
             },
         )
         .await
@@ -137,7 +138,7 @@ mod test_create_group {
 }
 
 mod test_get_group {
-    use std::assert_matches::assert_matches;
+    use std::assert_matches;
 
     use sqlx::PgPool;
 
@@ -186,4 +187,312 @@ mod test_get_group {
             }) if id == "4"
         );
     }
+
+    #[sqlx::test(fixtures("group"))]
+    async fn test_get_groups(pool: PgPool) {
+        let groups = backend::db::get_groups(&pool, &[1, 3, 4, 123456])
+            .await
+            .expect("failed to get groups");
+
+        let mut group_ids: Vec<_> = groups.iter().map(|g| g.group_id).collect();
+        group_ids.sort();
+        assert_eq!(group_ids, [1, 3], "missing and deleted groups are omitted");
+    }
+}
+
+mod test_list_groups {
+    use backend::db::KeysetCursor;
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("group"))]
+    async fn test_list_groups(pool: PgPool) {
+        let page = backend::db::list_groups(&pool, None, KeysetCursor::default())
+            .await
+            .expect("failed to list groups");
+
+        let names = page
+            .items
+            .iter()
+            .map(|g| g.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["group1", "group2", "group3"]);
+
+        let count = backend::db::count_groups(&pool, None)
+            .await
+            .expect("failed to count groups");
+        assert_eq!(count, 3, "deleted group should not be counted");
+    }
+
+    #[sqlx::test(fixtures("group"))]
+    async fn test_list_groups_backward(pool: PgPool) {
+        let page = backend::db::list_groups(
+            &pool,
+            None,
+            KeysetCursor {
+                last: Some(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("failed to list groups");
+
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].name, "group3");
+        assert!(page.has_previous_page);
+    }
+
+    #[sqlx::test(fixtures("group", "user", "role"))]
+    async fn test_list_owned_groups(pool: PgPool) {
+        let page = backend::db::list_groups(&pool, Some("teacher0"), KeysetCursor::default())
+            .await
+            .expect("failed to list groups");
+
+        let names = page
+            .items
+            .iter()
+            .map(|g| g.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["group1", "group2"]);
+
+        let count = backend::db::count_groups(&pool, Some("teacher1"))
+            .await
+            .expect("failed to count groups");
+        assert_eq!(count, 0);
+    }
+}
+
+mod test_update_group {
+    use std::assert_matches;
+
+    use backend::db::GroupUpdateParameter;
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("group"))]
+    async fn test_rename_group(pool: PgPool) {
+        backend::db::update_group(
+            &pool,
+            1,
+            GroupUpdateParameter {
+                name: Some("renamed"),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("failed to update group");
+
+        let group = backend::db::get_group(&pool, 1)
+            .await
+            .expect("failed to get group");
+        assert_eq!(group.name, "renamed");
+        assert_eq!(group.description, "description1");
+    }
+
+    #[sqlx::test(fixtures("group"))]
+    async fn test_update_group_deleted(pool: PgPool) {
+        let result = backend::db::update_group(
+            &pool,
+            4,
+            GroupUpdateParameter {
+                name: Some("renamed"),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_matches!(
+            result,
+            Err(backend::db::Error::NotFound {
+                entity: "group",
+                id,
+            }) if id == "4"
+        );
+    }
+}
+
+mod test_delete_group {
+    use std::assert_matches;
+
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_delete_group(pool: PgPool) {
+        backend::db::delete_group(&pool, 1)
+            .await
+            .expect("failed to delete group");
+
+        assert_matches!(
+            backend::db::get_group(&pool, 1).await,
+            Err(backend::db::Error::NotFound { .. })
+        );
+
+        let user = backend::db::get_or_initialize_user(&pool, "usergroup1")
+            .await
+            .expect("failed to get user");
+        assert_eq!(user.group_id, None, "members should be removed");
+    }
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_delete_group_deleted(pool: PgPool) {
+        let result = backend::db::delete_group(&pool, 4).await;
+
+        assert_matches!(
+            result,
+            Err(backend::db::Error::NotFound {
+                entity: "group",
+                id,
+            }) if id == "4"
+        );
+    }
+}
+
+mod test_set_user_group {
+    use std::assert_matches;
+
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_move_user(pool: PgPool) {
+        let user = backend::db::set_user_group(&pool, "usergroup1", Some(2))
+            .await
+            .expect("failed to set user group");
+        assert_eq!(user.group_id, Some(2));
+
+        let user = backend::db::set_user_group(&pool, "usergroup1", None)
+            .await
+            .expect("failed to set user group");
+        assert_eq!(user.group_id, None);
+    }
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_assign_new_user(pool: PgPool) {
+        let user = backend::db::set_user_group(&pool, "usernew0", Some(1))
+            .await
+            .expect("failed to set user group");
+        assert_eq!(user.group_id, Some(1));
+
+        let user = backend::db::get_or_initialize_user(&pool, "usernew0")
+            .await
+            .expect("failed to get user");
+        assert_eq!(user.group_id, Some(1));
+    }
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_deleted_group(pool: PgPool) {
+        let result = backend::db::set_user_group(&pool, "usergeneric0", Some(4)).await;
+        assert_matches!(result, Err(backend::db::Error::NotFound { .. }));
+    }
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_deleted_user(pool: PgPool) {
+        let result = backend::db::set_user_group(&pool, "userdeleted0", Some(1)).await;
+        assert_matches!(result, Err(backend::db::Error::NotFound { .. }));
+
+        let result = backend::db::set_user_group(&pool, "userdeleted0", None).await;
+        assert_matches!(result, Err(backend::db::Error::NotFound { .. }));
+    }
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_remove_unknown_user(pool: PgPool) {
+        let result = backend::db::set_user_group(&pool, "usernew0", None).await;
+        assert_matches!(result, Err(backend::db::Error::NotFound { .. }));
+
+        let user = backend::db::get_user(&pool, "usernew0")
+            .await
+            .expect("failed to get user");
+        assert!(user.is_none(), "the unknown user should not be created");
+    }
+}
+
+mod test_list_group_members {
+    use backend::db::KeysetCursor;
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_list_group_members(pool: PgPool) {
+        backend::db::set_user_group(&pool, "usergeneric0", Some(1))
+            .await
+            .expect("failed to set user group");
+
+        let page = backend::db::list_group_members(&pool, 1, KeysetCursor::default())
+            .await
+            .expect("failed to list group members");
+
+        let ids = page
+            .items
+            .iter()
+            .map(|u| u.user_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["usergeneric0", "usergroup1"]);
+
+        let count = backend::db::count_group_members(&pool, 1)
+            .await
+            .expect("failed to count group members");
+        assert_eq!(count, 2);
+    }
+}
+
+mod test_user_role {
+    use std::assert_matches;
+
+    use backend::db::UserRole;
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("group", "user", "role"))]
+    async fn test_get_user_role(pool: PgPool) {
+        let role = backend::db::get_user_role(&pool, "teacher0")
+            .await
+            .expect("failed to get role");
+        assert_eq!(role, Some(UserRole::Teacher));
+
+        let role = backend::db::get_user_role(&pool, "usergeneric0")
+            .await
+            .expect("failed to get role");
+        assert_eq!(role, Some(UserRole::Student), "student by default");
+
+        let role = backend::db::get_user_role(&pool, "userdeleted0")
+            .await
+            .expect("failed to get role");
+        assert_eq!(role, None);
+    }
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_set_user_role(pool: PgPool) {
+        let user = backend::db::set_user_role(&pool, "usergeneric0", UserRole::Admin)
+            .await
+            .expect("failed to set role");
+        assert_eq!(user.role, UserRole::Admin);
+
+        let user = backend::db::set_user_role(&pool, "usernew0", UserRole::Teacher)
+            .await
+            .expect("failed to set role");
+        assert_eq!(user.role, UserRole::Teacher, "initialized with the role");
+    }
+
+    #[sqlx::test(fixtures("group", "user"))]
+    async fn test_set_user_role_deleted(pool: PgPool) {
+        let user = backend::db::set_user_role(&pool, "userdeleted0", UserRole::Admin).await;
+
+        assert_matches!(
+            user,
+            Err(backend::db::Error::NotFound { entity: "user", .. })
+        );
+    }
+
+    #[sqlx::test(fixtures("group", "user", "role"))]
+    async fn test_is_user_in_owned_group(pool: PgPool) {
+        let owned = backend::db::is_user_in_owned_group(&pool, "teacher0", "usergroup2")
+            .await
+            .expect("failed to check");
+        assert!(owned);
+
+        let owned = backend::db::is_user_in_owned_group(&pool, "teacher0", "usergroup3")
+            .await
+            .expect("failed to check");
+        assert!(!owned, "group3 has no owner");
+
+        let owned = backend::db::is_user_in_owned_group(&pool, "teacher1", "usergroup1")
+            .await
+            .expect("failed to check");
+        assert!(!owned);
+    }
 }
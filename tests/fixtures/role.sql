@@ -0,0 +1,6 @@
+INSERT INTO dp_users (user_id, role) VALUES
+('teacher0', 'teacher'),
+('teacher1', 'teacher'),
+('admin0', 'admin');
+
+UPDATE dp_groups SET owner_id = 'teacher0' WHERE group_id IN (1, 2);
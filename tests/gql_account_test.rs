@@ -0,0 +1,164 @@
+#![cfg(all(test, feature = "test_database"))]
+
+mod common;
+
+use backend::{
+    db::{self, AttemptStatus},
+    gql::{self, auth::api_token, user_cache::UserCache},
+    logto::LogtoClient,
+};
+use common::logto::{APP_SECRET, MockLogto};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+const DELETE_MY_ACCOUNT: &str = "mutation { deleteMyAccount }";
+
+fn schema(pool: &PgPool, logto: Option<LogtoClient>) -> gql::Schema {
+    common::schema_builder(pool.clone(), None)
+        .data(logto)
+        .finish()
+}
+
+async fn deleted_at(pool: &PgPool, user_id: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    sqlx::query_scalar!(
+        "SELECT deleted_at FROM dp_users WHERE user_id = $1",
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .expect("failed to fetch user")
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_delete_my_account(pool: PgPool) {
+    let logto = MockLogto::serve(&["usergroup1", "usergroup2"]).await;
+    let schema = schema(&pool, Some(logto.client(APP_SECRET)));
+
+    let response = schema
+        .execute(common::request(DELETE_MY_ACCOUNT, "usergroup1", []))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+    assert_eq!(logto.users(), vec!["usergroup2"]);
+    assert!(deleted_at(&pool, "usergroup1").await.is_some());
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_delete_uninitialized_account(pool: PgPool) {
+    let schema = schema(&pool, None);
+
+    let response = schema
+        .execute(common::request(DELETE_MY_ACCOUNT, "usernew0", []))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+    assert!(deleted_at(&pool, "usernew0").await.is_some());
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_delete_my_account_logto_failed(pool: PgPool) {
+    let logto = MockLogto::serve(&["usergroup1"]).await;
+    logto.fail(true);
+    let schema = schema(&pool, Some(logto.client(APP_SECRET)));
+
+    let response = schema
+        .execute(common::request(DELETE_MY_ACCOUNT, "usergroup1", []))
+        .await;
+    assert_eq!(response.errors.len(), 1);
+
+    assert_eq!(logto.users(), vec!["usergroup1"]);
+    assert!(
+        deleted_at(&pool, "usergroup1").await.is_none(),
+        "the account is kept so that the deletion can be retried"
+    );
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_deleted_account_rejected(pool: PgPool) {
+    let cache = UserCache::default();
+    let token = common::jwks::shared_secret_token(
+        common::SHARED_SECRET,
+        jsonwebtoken::Algorithm::HS256,
+        "usergroup1",
+        "",
+    );
+
+    let body = common::send(&pool, &cache, &token, "{ user { userId } }").await;
+    assert_eq!(body["errors"], Value::Null);
+
+    let body = common::send(&pool, &cache, &token, DELETE_MY_ACCOUNT).await;
+    assert_eq!(body["data"]["deleteMyAccount"], true);
+
+    let body = common::send(&pool, &cache, &token, "{ user { userId } }").await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "USER_DELETED");
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_delete_my_account_with_api_token(pool: PgPool) {
+    let (token, token_hash) = api_token::generate();
+    db::create_api_token(
+        &pool,
+        db::ApiTokenCreateParameter {
+            user_id: "usergroup1",
+            name: "ci",
+            token_hash: &token_hash,
+            scopes: &[],
+            expires_at: None,
+        },
+    )
+    .await
+    .expect("failed to create API token");
+
+    let body = common::send(&pool, &UserCache::default(), &token, DELETE_MY_ACCOUNT).await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "UNAUTHORIZED");
+    assert!(deleted_at(&pool, "usergroup1").await.is_none());
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_export_my_data(pool: PgPool) {
+    let attempt_id =
+        db::create_attempt_event(&pool, "usergroup1", 1, "SELECT 1;", AttemptStatus::Passed)
+            .await
+            .expect("failed to create attempt event");
+    db::create_attempt_event(&pool, "usergroup2", 1, "SELECT 2;", AttemptStatus::Failed)
+        .await
+        .expect("failed to create attempt event");
+    let solution_id = db::create_solution_event(&pool, "usergroup1", 2)
+        .await
+        .expect("failed to create solution event");
+
+    let schema = schema(&pool, None);
+    let response = schema
+        .execute(async_graphql::Request::new("{ exportMyData }"))
+        .await;
+    assert_eq!(response.errors.len(), 1, "a login is required");
+
+    let response = schema
+        .execute(common::request("{ exportMyData }", "usergroup1", []))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+    let data = response.data.into_json().expect("invalid data");
+    let export = &data["exportMyData"];
+
+    assert_eq!(export["profile"]["userId"], "usergroup1");
+    assert_eq!(export["profile"]["groupId"], 1);
+    assert_eq!(export["profile"]["role"], "student");
+
+    let attempts = export["attempts"].as_array().expect("no attempts");
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0]["id"], attempt_id);
+    assert_eq!(attempts[0]["query"], "SELECT 1;");
+    assert_eq!(attempts[0]["status"], "passed");
+
+    let solution_views = export["solutionViews"]
+        .as_array()
+        .expect("no solution views");
+    assert_eq!(solution_views.len(), 1);
+    assert_eq!(
+        solution_views[0]["id"],
+        json!(solution_id),
+        "{solution_views:?}"
+    );
+    assert_eq!(solution_views[0]["questionId"], 2);
+}
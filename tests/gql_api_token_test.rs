@@ -0,0 +1,138 @@
+#![cfg(all(test, feature = "test_database"))]
+
+mod common;
+
+use backend::gql::{auth::Scope, user_cache::UserCache};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+const CREATE_TOKEN: &str = r#"
+    mutation {
+        createApiToken(name: "ci", scopes: [READ_ANSWER]) {
+            token
+            apiToken { id name scopes }
+        }
+    }
+"#;
+
+async fn create_token(pool: &PgPool, scopes: &[Scope]) -> Value {
+    let schema = common::schema(pool.clone(), None);
+    let response = schema
+        .execute(common::request(
+            CREATE_TOKEN,
+            "usergeneric0",
+            scopes.iter().copied(),
+        ))
+        .await;
+
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().expect("invalid data")["createApiToken"].clone()
+}
+
+async fn send(pool: &PgPool, token: &str, query: &str) -> Value {
+    common::send(pool, &UserCache::default(), token, query).await
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_authenticate_with_api_token(pool: PgPool) {
+    let created = create_token(&pool, &[Scope::ReadAnswer, Scope::ManageUser]).await;
+    let token = created["token"].as_str().expect("no token");
+    assert!(token.starts_with("dpat_"));
+    assert_eq!(
+        created["apiToken"],
+        json!({ "id": created["apiToken"]["id"], "name": "ci", "scopes": ["READ_ANSWER"] })
+    );
+
+    let body = send(
+        &pool,
+        token,
+        "{ user { userId } apiTokens { name lastUsedAt } }",
+    )
+    .await;
+    assert_eq!(body["data"]["user"]["userId"], "usergeneric0");
+    assert_eq!(body["data"]["apiTokens"][0]["name"], "ci");
+    assert!(body["data"]["apiTokens"][0]["lastUsedAt"].is_string());
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_api_token_cannot_manage_api_tokens(pool: PgPool) {
+    let created = create_token(&pool, &[Scope::ReadAnswer]).await;
+    let token = created["token"].as_str().expect("no token");
+    let id = &created["apiToken"]["id"];
+
+    // Otherwise a leaked token could outlive its expiry and revocation.
+    let body = send(
+        &pool,
+        token,
+        r#"mutation { createApiToken(name: "nested", scopes: [READ_ANSWER]) { token } }"#,
+    )
+    .await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "UNAUTHORIZED");
+
+    let body = send(
+        &pool,
+        token,
+        &format!("mutation {{ revokeApiToken(id: {id}) }}"),
+    )
+    .await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "UNAUTHORIZED");
+
+    let body = send(&pool, token, "{ apiTokens { name } }").await;
+    assert_eq!(body["data"]["apiTokens"], json!([{ "name": "ci" }]));
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_cannot_grant_missing_scope(pool: PgPool) {
+    let schema = common::schema(pool.clone(), None);
+    let response = schema
+        .execute(common::request(CREATE_TOKEN, "usergeneric0", []))
+        .await;
+
+    let extensions = response.errors[0]
+        .extensions
+        .as_ref()
+        .expect("no extensions");
+    assert_eq!(
+        extensions.get("code"),
+        Some(&async_graphql::Value::from("UNAUTHORIZED"))
+    );
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_revoked_api_token(pool: PgPool) {
+    let created = create_token(&pool, &[Scope::ReadAnswer]).await;
+    let token = created["token"].as_str().expect("no token");
+    let id = &created["apiToken"]["id"];
+
+    let schema = common::schema(pool.clone(), None);
+    let response = schema
+        .execute(common::request(
+            format!("mutation {{ revokeApiToken(id: {id}) }}"),
+            "usergeneric0",
+            [],
+        ))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+    let body = send(&pool, token, "{ user { userId } }").await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "INVALID_JWT_TOKEN");
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_unknown_api_token(pool: PgPool) {
+    let body = send(&pool, "dpat_unknown", "{ user { userId } }").await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "INVALID_JWT_TOKEN");
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_api_token_lookup_failed(pool: PgPool) {
+    pool.close().await;
+
+    let body = send(&pool, "dpat_unknown", "{ user { userId } }").await;
+    let extensions = &body["errors"][0]["extensions"];
+    assert_eq!(extensions["code"], "INTERNAL_ERROR");
+    assert_eq!(
+        extensions["details"], "Unable to verify the token.",
+        "the database error is not exposed"
+    );
+}
@@ -0,0 +1,233 @@
+#![cfg(all(test, feature = "test_database"))]
+
+mod common;
+
+use backend::gql::{
+    auth::{Auth, Role, Scope},
+    user_cache::UserCache,
+};
+use common::{SHARED_SECRET, jwks::shared_secret_token_with_roles};
+use jsonwebtoken::Algorithm;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+async fn run(pool: &PgPool, query: &str, sub: &str, scopes: &[Scope]) -> async_graphql::Response {
+    run_with_auth(pool, query, Auth::new(sub, scopes.iter().copied())).await
+}
+
+async fn run_with_auth(pool: &PgPool, query: &str, auth: Auth) -> async_graphql::Response {
+    let schema = common::schema(pool.clone(), None);
+    schema.execute(common::request_with_auth(query, auth)).await
+}
+
+fn into_data(response: async_graphql::Response) -> Value {
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().expect("invalid data")
+}
+
+fn error_code(response: &async_graphql::Response) -> Option<String> {
+    let extensions = response.errors.first()?.extensions.as_ref()?;
+    match extensions.get("code")? {
+        async_graphql::Value::String(code) => Some(code.clone()),
+        _ => None,
+    }
+}
+
+#[sqlx::test(fixtures("group", "user", "role", "schema"))]
+async fn test_content_requires_admin(pool: PgPool) {
+    let query = r#"mutation { deleteSchema(id: "school") }"#;
+
+    // The content is shared by all the groups, so the teachers cannot edit it.
+    for sub in ["usergeneric0", "teacher0"] {
+        let response = run(&pool, query, sub, &[Scope::WriteResource]).await;
+        assert_eq!(
+            error_code(&response).as_deref(),
+            Some("UNAUTHORIZED"),
+            "{sub}"
+        );
+    }
+
+    let response = run(&pool, query, "admin0", &[]).await;
+    assert_eq!(
+        error_code(&response).as_deref(),
+        Some("UNAUTHORIZED"),
+        "the scope is still required"
+    );
+
+    let data = into_data(run(&pool, query, "admin0", &[Scope::WriteResource]).await);
+    assert_eq!(data["deleteSchema"], "school");
+}
+
+#[sqlx::test(fixtures("group", "user", "role"))]
+async fn test_teacher_lists_own_groups(pool: PgPool) {
+    let query = "{ groups { totalCount edges { node { name } } } }";
+
+    let data = into_data(run(&pool, query, "teacher0", &[Scope::ManageUser]).await);
+    assert_eq!(
+        data,
+        json!({ "groups": {
+            "totalCount": 2,
+            "edges": [{ "node": { "name": "group1" } }, { "node": { "name": "group2" } }],
+        } })
+    );
+
+    let data = into_data(run(&pool, query, "admin0", &[Scope::ManageUser]).await);
+    assert_eq!(data["groups"]["totalCount"], 3);
+
+    let response = run(&pool, query, "usergeneric0", &[Scope::ManageUser]).await;
+    assert_eq!(error_code(&response).as_deref(), Some("UNAUTHORIZED"));
+}
+
+#[sqlx::test(fixtures("group", "user", "role"))]
+async fn test_teacher_manages_own_groups(pool: PgPool) {
+    let query = r#"mutation { updateGroup(id: 1, name: "renamed") { name } }"#;
+
+    let response = run(&pool, query, "teacher1", &[Scope::ManageUser]).await;
+    assert_eq!(error_code(&response).as_deref(), Some("UNAUTHORIZED"));
+
+    let data = into_data(run(&pool, query, "teacher0", &[Scope::ManageUser]).await);
+    assert_eq!(data["updateGroup"]["name"], "renamed");
+
+    let query = r#"mutation { removeUserFromGroup(userId: "usergroup1") { groupId } }"#;
+
+    // Neither the other teachers nor the member themselves can remove them.
+    for sub in ["teacher1", "usergroup1"] {
+        let response = run(&pool, query, sub, &[Scope::ManageUser]).await;
+        assert_eq!(
+            error_code(&response).as_deref(),
+            Some("UNAUTHORIZED"),
+            "{sub}"
+        );
+    }
+
+    let data = into_data(run(&pool, query, "teacher0", &[Scope::ManageUser]).await);
+    assert_eq!(data["removeUserFromGroup"]["groupId"], Value::Null);
+}
+
+#[sqlx::test(fixtures("group", "user", "role"))]
+async fn test_teacher_creates_own_group(pool: PgPool) {
+    let query = r#"mutation { createGroup(name: "new") { ownerId } }"#;
+    let data = into_data(run(&pool, query, "teacher1", &[Scope::ManageUser]).await);
+    assert_eq!(data["createGroup"]["ownerId"], "teacher1");
+
+    let query = r#"mutation { createGroup(name: "new", ownerId: "teacher0") { ownerId } }"#;
+    let response = run(&pool, query, "teacher1", &[Scope::ManageUser]).await;
+    assert_eq!(error_code(&response).as_deref(), Some("UNAUTHORIZED"));
+
+    let data = into_data(run(&pool, query, "admin0", &[Scope::ManageUser]).await);
+    assert_eq!(data["createGroup"]["ownerId"], "teacher0");
+}
+
+#[sqlx::test(fixtures("group", "user", "role"))]
+async fn test_member_data(pool: PgPool) {
+    let query = r#"
+        {
+            groups {
+                edges { node { members { edges { node { userId progress { total { solved } } } } } } }
+            }
+        }
+    "#;
+
+    let data = into_data(run(&pool, query, "teacher0", &[Scope::ManageUser]).await);
+    let members = &data["groups"]["edges"][0]["node"]["members"]["edges"];
+    assert_eq!(members[0]["node"]["userId"], "usergroup1");
+    assert_eq!(members[0]["node"]["progress"]["total"]["solved"], 0);
+}
+
+#[sqlx::test(fixtures("group", "user", "role"))]
+async fn test_role_from_token(pool: PgPool) {
+    let query = r#"mutation { setUserRole(userId: "usergeneric1", role: TEACHER) { role } }"#;
+
+    let response = run(&pool, query, "usergeneric0", &[Scope::ManageUser]).await;
+    assert_eq!(error_code(&response).as_deref(), Some("UNAUTHORIZED"));
+
+    let auth = Auth::new("usergeneric0", [Scope::ManageUser]).with_role(Role::Admin);
+    let data = into_data(run_with_auth(&pool, query, auth).await);
+    assert_eq!(data["setUserRole"]["role"], "TEACHER");
+}
+
+#[sqlx::test(fixtures("group", "user", "role"))]
+async fn test_teacher_enrolls_own_students(pool: PgPool) {
+    let group = into_data(
+        run(
+            &pool,
+            r#"mutation { createGroup(name: "new") { groupId } }"#,
+            "teacher1",
+            &[Scope::ManageUser],
+        )
+        .await,
+    )["createGroup"]["groupId"]
+        .clone();
+    let enroll = |user_id: &str| {
+        format!(
+            r#"mutation {{ addUserToGroup(userId: "{user_id}", groupId: {group}) {{ groupId }} }}"#
+        )
+    };
+
+    // The students of the other teachers and the staff cannot be taken.
+    for user_id in ["usergroup1", "teacher0", "admin0"] {
+        let response = run(&pool, &enroll(user_id), "teacher1", &[Scope::ManageUser]).await;
+        assert_eq!(
+            error_code(&response).as_deref(),
+            Some("UNAUTHORIZED"),
+            "{user_id}"
+        );
+    }
+
+    for user_id in ["usergeneric0", "usernew0"] {
+        let data = into_data(run(&pool, &enroll(user_id), "teacher1", &[Scope::ManageUser]).await);
+        assert_eq!(data["addUserToGroup"]["groupId"], group, "{user_id}");
+    }
+
+    let data = into_data(run(&pool, &enroll("usergroup1"), "admin0", &[Scope::ManageUser]).await);
+    assert_eq!(data["addUserToGroup"]["groupId"], group);
+}
+
+#[sqlx::test(fixtures("group", "user", "role"))]
+async fn test_token_role_not_stored(pool: PgPool) {
+    // usergeneric1 is stored as a student, and is an admin by the token.
+    let token = |roles: &[&str]| {
+        shared_secret_token_with_roles(
+            SHARED_SECRET,
+            Algorithm::HS256,
+            "usergeneric1",
+            "write:resource",
+            roles,
+        )
+    };
+    let query = r#"mutation { deleteSchema(id: "missing") }"#;
+    let user_cache = UserCache::default();
+
+    let body = common::send(&pool, &user_cache, &token(&["admin"]), query).await;
+    assert_eq!(
+        body["errors"][0]["extensions"]["code"], "NOT_FOUND",
+        "{body}"
+    );
+
+    let role = backend::db::get_user_role(&pool, "usergeneric1")
+        .await
+        .expect("failed to get role");
+    assert_eq!(role, Some(backend::db::UserRole::Student));
+
+    // Revoking the role at the identity provider takes effect.
+    let body = common::send(&pool, &user_cache, &token(&[]), query).await;
+    assert_eq!(
+        body["errors"][0]["extensions"]["code"], "UNAUTHORIZED",
+        "{body}"
+    );
+}
+
+#[sqlx::test(fixtures("group", "user", "role"))]
+async fn test_teacher_by_token_not_enrolled(pool: PgPool) {
+    // usergeneric1 is stored as a student, and is a teacher by the token.
+    sqlx::query("UPDATE dp_groups SET owner_id = 'usergeneric1' WHERE group_id = 3")
+        .execute(&pool)
+        .await
+        .expect("failed to set the owner");
+
+    let query = r#"mutation { addUserToGroup(userId: "usergeneric1", groupId: 3) { groupId } }"#;
+    let auth = Auth::new("usergeneric1", [Scope::ManageUser]).with_role(Role::Teacher);
+
+    let response = run_with_auth(&pool, query, auth).await;
+    assert_eq!(error_code(&response).as_deref(), Some("UNAUTHORIZED"));
+}
@@ -0,0 +1,415 @@
+#![cfg(all(test, feature = "test_database"))]
+
+mod common;
+
+use std::sync::atomic::Ordering;
+
+use backend::{
+    db::{self, AttemptStatus},
+    gql::{
+        auth::Scope,
+        rate_limit::{RateLimiter, RateLimiterConfig},
+    },
+    rpc::dbrunner::{Cell, DataRow, HeaderRow, retrieve_query_response::Kind},
+};
+use common::dbrunner::MockDbRunner;
+use futures_util::StreamExt;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+const SCOPES: [Scope; 2] = [Scope::Execution, Scope::ReadAnswer];
+
+/// Execute `sql` against question 1 as usergeneric0 and resolve `fields` on
+/// the result.
+async fn execute(pool: &PgPool, sql: &str, fields: &str) -> async_graphql::Response {
+    let dbrunner = MockDbRunner::new(pool.clone()).serve().await;
+    let schema = common::schema(pool.clone(), Some(dbrunner));
+
+    let query = format!(
+        r#"
+        mutation($sql: String!) {{
+            execute(questionId: 1, sql: $sql) {{
+                __typename
+                ... on ExecuteSuccessResult {{ {fields} }}
+                ... on ExecuteFailedResult {{ error }}
+            }}
+        }}
+        "#
+    );
+    let request = common::request(query, "usergeneric0", SCOPES)
+        .variables(async_graphql::Variables::from_json(json!({ "sql": sql })));
+
+    schema.execute(request).await
+}
+
+fn into_data(response: async_graphql::Response) -> Value {
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().expect("invalid data")["execute"].clone()
+}
+
+async fn attempt_status(pool: &PgPool) -> (AttemptStatus, Option<String>) {
+    let event = sqlx::query!(
+        r#"
+        SELECT status AS "status: AttemptStatus", error
+        FROM dp_attempt_events
+        WHERE user_id = 'usergeneric0'
+        "#
+    )
+    .fetch_one(pool)
+    .await
+    .expect("no attempt recorded");
+
+    (event.status, event.error)
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_rows(pool: PgPool) {
+    let response = execute(
+        &pool,
+        "SELECT * FROM products WHERE product_name = 'Laptop';",
+        "rows { column rows }",
+    )
+    .await;
+
+    assert_eq!(
+        into_data(response),
+        json!({
+            "__typename": "ExecuteSuccessResult",
+            "rows": {
+                "column": ["product_id", "product_name", "price", "stock"],
+                "rows": [["1", "Laptop", "999.99", "10"]],
+            },
+        })
+    );
+    assert_eq!(attempt_status(&pool).await, (AttemptStatus::Pending, None));
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_same(pool: PgPool) {
+    let response = execute(&pool, "SELECT * FROM products WHERE price > 100;", "same").await;
+
+    assert_eq!(into_data(response)["same"], json!(true));
+    assert_eq!(attempt_status(&pool).await, (AttemptStatus::Passed, None));
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_not_same(pool: PgPool) {
+    let response = execute(
+        &pool,
+        "SELECT * FROM products ORDER BY product_id;",
+        "same diff { missingRows extraRows orderDiffers }",
+    )
+    .await;
+
+    assert_eq!(
+        into_data(response),
+        json!({
+            "__typename": "ExecuteSuccessResult",
+            "same": false,
+            "diff": {
+                "missingRows": [],
+                "extraRows": [["2", "Mouse", "19.99", "100"]],
+                "orderDiffers": false,
+            },
+        })
+    );
+    assert_eq!(attempt_status(&pool).await, (AttemptStatus::Failed, None));
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_answer_run_once(pool: PgPool) {
+    let dbrunner = MockDbRunner::new(pool.clone());
+    let runs = dbrunner.runs();
+    let schema = common::schema(pool.clone(), Some(dbrunner.serve().await));
+
+    let request = common::request(
+        r#"
+        mutation {
+            execute(questionId: 1, sql: "SELECT 1;") {
+                ... on ExecuteSuccessResult { same diff { orderDiffers } }
+            }
+        }
+        "#,
+        "usergeneric0",
+        SCOPES,
+    );
+    let data = into_data(schema.execute(request).await);
+
+    assert_eq!(data["same"], json!(false));
+    assert_eq!(
+        runs.load(Ordering::SeqCst),
+        2,
+        "the user query and the answer run once each"
+    );
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_aliases_resolved_once(pool: PgPool) {
+    let dbrunner = MockDbRunner::new(pool.clone());
+    let runs = dbrunner.runs();
+    let schema = common::schema(pool.clone(), Some(dbrunner.serve().await));
+
+    let request = common::request(
+        r#"
+        mutation {
+            execute(questionId: 1, sql: "SELECT 1;") {
+                ... on ExecuteSuccessResult {
+                    a: same
+                    b: same
+                    c: diff { orderDiffers }
+                    d: diff { orderDiffers }
+                }
+            }
+        }
+        "#,
+        "usergeneric0",
+        SCOPES,
+    );
+    let data = into_data(schema.execute(request).await);
+
+    assert_eq!(data["a"], data["b"]);
+    assert_eq!(data["c"], data["d"]);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_diff_columns(pool: PgPool) {
+    let response = execute(
+        &pool,
+        "SELECT product_id AS id, product_name FROM products WHERE product_id = 1;",
+        "diff { missingRows extraRows columnMismatches { index actual expected } }",
+    )
+    .await;
+
+    let diff = &into_data(response)["diff"];
+    assert_eq!(
+        diff["columnMismatches"],
+        json!([
+            { "index": 0, "actual": "id", "expected": "product_id" },
+            { "index": 2, "actual": null, "expected": "price" },
+            { "index": 3, "actual": null, "expected": "stock" },
+        ])
+    );
+    assert_eq!(
+        diff["missingRows"],
+        json!([["1", "Laptop", "999.99", "10"]])
+    );
+    assert_eq!(diff["extraRows"], json!([["1", "Laptop"]]));
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_invalid_sql(pool: PgPool) {
+    let response = execute(&pool, "SELECT * FROM no_such_table;", "same").await;
+
+    let data = into_data(response);
+    assert_eq!(data["__typename"], json!("ExecuteFailedResult"));
+    let error = data["error"].as_str().expect("no error message");
+    assert!(error.contains("no_such_table"), "{error}");
+
+    let (status, recorded_error) = attempt_status(&pool).await;
+    assert_eq!(status, AttemptStatus::Failed);
+    assert_eq!(recorded_error.as_deref(), Some(error));
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_untyped_response(pool: PgPool) {
+    let dbrunner = MockDbRunner::new(pool.clone())
+        .with_untyped_response("SELECT 1;")
+        .serve()
+        .await;
+    let schema = common::schema(pool.clone(), Some(dbrunner));
+    let request = common::request(
+        r#"mutation { execute(questionId: 1, sql: "SELECT 1;") { __typename } }"#,
+        "usergeneric0",
+        SCOPES,
+    );
+
+    let response = schema.execute(request).await;
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].message,
+        "Internal error: Unknown response type."
+    );
+
+    let (status, recorded_error) = attempt_status(&pool).await;
+    assert_eq!(status, AttemptStatus::Failed, "not left pending");
+    assert_eq!(recorded_error.as_deref(), Some("Unknown response type."));
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_dbrunner_unavailable(pool: PgPool) {
+    let schema = common::schema(pool, None);
+    let request = common::request(
+        r#"mutation { execute(questionId: 1, sql: "SELECT 1;") { __typename } }"#,
+        "usergeneric0",
+        SCOPES,
+    );
+
+    let response = schema.execute(request).await;
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].message,
+        "Internal error: Database runner is not available."
+    );
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_execute_requires_scope(pool: PgPool) {
+    let schema = common::schema(pool, None);
+    let request = common::request(
+        r#"mutation { execute(questionId: 1, sql: "SELECT 1;") { __typename } }"#,
+        "usergeneric0",
+        [Scope::ReadAnswer],
+    );
+
+    let response = schema.execute(request).await;
+    assert_eq!(response.errors.len(), 1);
+    assert!(response.errors[0].message.starts_with("Unauthorized"));
+}
+
+/// Subscribe to `queryRows` of `query_id` as `sub`, and collect the responses.
+async fn query_rows(schema: &backend::gql::Schema, query_id: &str, sub: &str) -> Vec<Value> {
+    let query = format!(
+        r#"
+        subscription {{
+            queryRows(queryId: "{query_id}", batchSize: 1) {{
+                __typename
+                ... on TableHeader {{ column }}
+                ... on TableRows {{ rows }}
+            }}
+        }}
+        "#
+    );
+
+    schema
+        .execute_stream(common::request(query, sub, SCOPES))
+        .map(|response| match response.errors.first() {
+            Some(error) => {
+                let code = error.extensions.as_ref().and_then(|e| e.get("code"));
+                json!({ "error": code })
+            }
+            None => response.data.into_json().expect("invalid data")["queryRows"].clone(),
+        })
+        .collect()
+        .await
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_query_rows(pool: PgPool) {
+    let dbrunner = MockDbRunner::new(pool.clone()).serve().await;
+    let schema = common::schema(pool.clone(), Some(dbrunner));
+
+    let request = common::request(
+        r#"
+        mutation {
+            execute(questionId: 1, sql: "SELECT product_id FROM products ORDER BY product_id;") {
+                ... on ExecuteSuccessResult { queryId }
+            }
+        }
+        "#,
+        "usergeneric0",
+        SCOPES,
+    );
+    let data = into_data(schema.execute(request).await);
+    let query_id = data["queryId"].as_str().expect("no query ID");
+
+    assert_eq!(
+        query_rows(&schema, query_id, "usergeneric0").await,
+        vec![
+            json!({ "__typename": "TableHeader", "column": ["product_id"] }),
+            json!({ "__typename": "TableRows", "rows": [["1"]] }),
+            json!({ "__typename": "TableRows", "rows": [["2"]] }),
+        ]
+    );
+
+    // The results of the other users are not exposed.
+    assert_eq!(
+        query_rows(&schema, query_id, "usergroup1").await,
+        vec![json!({ "error": "NOT_FOUND" })]
+    );
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_query_rows_rate_limited(pool: PgPool) {
+    let dbrunner = MockDbRunner::new(pool.clone()).serve().await;
+    let schema = common::schema_builder(pool.clone(), Some(dbrunner))
+        .data(RateLimiter::new(RateLimiterConfig {
+            burst: 1,
+            per_minute: 1,
+            max_concurrent: 1,
+        }))
+        .finish();
+
+    let request = common::request(
+        r#"
+        mutation {
+            execute(questionId: 1, sql: "SELECT 1;") {
+                ... on ExecuteSuccessResult { queryId }
+            }
+        }
+        "#,
+        "usergeneric0",
+        SCOPES,
+    );
+    let data = into_data(schema.execute(request).await);
+    let query_id = data["queryId"].as_str().expect("no query ID");
+
+    assert_eq!(
+        query_rows(&schema, query_id, "usergeneric0").await,
+        vec![json!({ "error": "RATE_LIMITED" })]
+    );
+}
+
+/// Stream the result from a dbrunner which responds with `messages`.
+async fn query_malformed_rows(pool: &PgPool, messages: Vec<Kind>) -> Vec<Value> {
+    let attempt_id =
+        db::create_attempt_event(pool, "usergeneric0", 1, "SELECT 1;", AttemptStatus::Pending)
+            .await
+            .expect("failed to create attempt event");
+    db::set_attempt_event_query_id(pool, attempt_id, "malformed")
+        .await
+        .expect("failed to set query ID");
+
+    let dbrunner = MockDbRunner::new(pool.clone())
+        .with_stream("malformed", messages)
+        .serve()
+        .await;
+    let schema = common::schema(pool.clone(), Some(dbrunner));
+
+    query_rows(&schema, "malformed", "usergeneric0").await
+}
+
+fn header() -> Kind {
+    Kind::Header(HeaderRow {
+        cells: vec!["?column?".to_string()],
+    })
+}
+
+fn row(value: &str) -> Kind {
+    Kind::Row(DataRow {
+        cells: vec![Cell {
+            value: Some(value.to_string()),
+        }],
+    })
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_query_rows_second_header(pool: PgPool) {
+    let chunks = query_malformed_rows(&pool, vec![header(), row("1"), header(), row("2")]).await;
+
+    assert_eq!(
+        chunks,
+        vec![
+            json!({ "__typename": "TableHeader", "column": ["?column?"] }),
+            json!({ "__typename": "TableRows", "rows": [["1"]] }),
+            json!({ "error": "INTERNAL_ERROR" }),
+        ]
+    );
+}
+
+#[sqlx::test(fixtures("group", "user", "schema", "question"))]
+async fn test_query_rows_without_header(pool: PgPool) {
+    let chunks = query_malformed_rows(&pool, vec![row("1"), row("2")]).await;
+
+    assert_eq!(chunks, vec![json!({ "error": "INTERNAL_ERROR" })]);
+}
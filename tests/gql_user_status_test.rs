@@ -0,0 +1,73 @@
+#![cfg(all(test, feature = "test_database"))]
+
+mod common;
+
+use std::time::Duration;
+
+use backend::{db, gql::user_cache::UserCache};
+use common::{SHARED_SECRET, jwks::shared_secret_token};
+use jsonwebtoken::Algorithm;
+use serde_json::Value;
+use sqlx::PgPool;
+
+const QUERY: &str = "{ apiTokens { name } }";
+
+fn token(sub: &str) -> String {
+    shared_secret_token(SHARED_SECRET, Algorithm::HS256, sub, "read:answer")
+}
+
+fn error_code(body: &Value) -> &Value {
+    &body["errors"][0]["extensions"]["code"]
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_deleted_user_rejected(pool: PgPool) {
+    let body = common::send(&pool, &UserCache::default(), &token("userdeleted0"), QUERY).await;
+
+    assert_eq!(error_code(&body), "USER_DELETED");
+    assert_eq!(body["data"], Value::Null);
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_active_user_accepted(pool: PgPool) {
+    for sub in ["usergroup1", "usernew0"] {
+        let body = common::send(&pool, &UserCache::default(), &token(sub), QUERY).await;
+        assert_eq!(body["errors"], Value::Null, "{sub}: {body}");
+    }
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_user_cached(pool: PgPool) {
+    let cache = UserCache::new(Duration::from_secs(3600));
+    let token = token("usergroup1");
+
+    let body = common::send(&pool, &cache, &token, QUERY).await;
+    assert_eq!(body["errors"], Value::Null);
+
+    db::delete_user(&pool, "usergroup1")
+        .await
+        .expect("failed to delete user");
+
+    let body = common::send(&pool, &cache, &token, QUERY).await;
+    assert_eq!(body["errors"], Value::Null, "the cached user is used");
+
+    cache.invalidate("usergroup1");
+    let body = common::send(&pool, &cache, &token, QUERY).await;
+    assert_eq!(error_code(&body), "USER_DELETED");
+}
+
+#[sqlx::test(fixtures("group", "user"))]
+async fn test_user_cache_expired(pool: PgPool) {
+    let cache = UserCache::new(Duration::ZERO);
+    let token = token("usergroup1");
+
+    let body = common::send(&pool, &cache, &token, QUERY).await;
+    assert_eq!(body["errors"], Value::Null);
+
+    db::delete_user(&pool, "usergroup1")
+        .await
+        .expect("failed to delete user");
+
+    let body = common::send(&pool, &cache, &token, QUERY).await;
+    assert_eq!(error_code(&body), "USER_DELETED");
+}
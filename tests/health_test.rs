@@ -0,0 +1,89 @@
+#![cfg(all(test, feature = "test_database"))]
+
+mod common;
+
+use backend::{health, rpc};
+use common::dbrunner::MockDbRunner;
+use poem::{EndpointExt, Route, get, http::StatusCode, test::TestClient};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+async fn ready(pool: PgPool, dbrunner: Option<rpc::DbRunnerClient>) -> (StatusCode, Value) {
+    let app = Route::new()
+        .at("/health/ready", get(health::ready))
+        .data(pool)
+        .data(dbrunner);
+
+    let response = TestClient::new(app).get("/health/ready").send().await;
+    let status = response.0.status();
+    let body = response
+        .0
+        .into_body()
+        .into_json()
+        .await
+        .expect("invalid json");
+
+    (status, body)
+}
+
+#[sqlx::test]
+async fn test_ready(pool: PgPool) {
+    let dbrunner = MockDbRunner::new(pool.clone()).serve().await;
+
+    let (status, body) = ready(pool, Some(dbrunner)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({
+            "status": "ok",
+            "postgres": { "status": "ok" },
+            "dbrunner": { "status": "ok" },
+        })
+    );
+}
+
+#[sqlx::test]
+async fn test_ready_without_dbrunner(pool: PgPool) {
+    let (status, body) = ready(pool, None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "error");
+    assert_eq!(body["postgres"]["status"], "ok");
+    assert_eq!(
+        body["dbrunner"],
+        json!({ "status": "error", "error": "dbrunner is not configured" })
+    );
+}
+
+#[sqlx::test]
+async fn test_ready_dbrunner_unreachable(pool: PgPool) {
+    let dbrunner = rpc::DbRunnerClient::connect_lazy(
+        // Nothing listens on the discard port.
+        "http://127.0.0.1:9".to_string(),
+        rpc::DbRunnerConfig::default(),
+    )
+    .expect("invalid address");
+
+    let (status, body) = ready(pool, Some(dbrunner)).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["dbrunner"]["status"], "error");
+}
+
+#[sqlx::test]
+async fn test_ready_postgres_closed(pool: PgPool) {
+    let dbrunner = MockDbRunner::new(pool.clone()).serve().await;
+    let closed = pool.clone();
+    closed.close().await;
+
+    let (status, body) = ready(closed, Some(dbrunner)).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["postgres"]["status"], "error");
+}
+
+#[tokio::test]
+async fn test_live() {
+    let app = Route::new().at("/health/live", get(health::live));
+
+    let response = TestClient::new(app).get("/health/live").send().await;
+    response.assert_status_is_ok();
+    response.assert_text("OK").await;
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use std::time::Duration;
+
+use backend::logto::{self, LogtoClient, LogtoConfig};
+use common::logto::{APP_SECRET, MockLogto};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn test_delete_user() {
+    let mock = MockLogto::serve(&["user-1", "user-2"]).await;
+    let client = mock.client(APP_SECRET);
+
+    client
+        .delete_user("user-1")
+        .await
+        .expect("failed to delete user");
+    assert_eq!(mock.users(), vec!["user-2"]);
+
+    client
+        .delete_user("user-2")
+        .await
+        .expect("failed to delete user");
+    assert!(mock.users().is_empty());
+    assert_eq!(mock.token_requests(), 1, "the access token is cached");
+}
+
+#[tokio::test]
+async fn test_delete_missing_user() {
+    let mock = MockLogto::serve(&[]).await;
+    let client = mock.client(APP_SECRET);
+
+    client
+        .delete_user("user-1")
+        .await
+        .expect("missing user should be ignored");
+}
+
+#[tokio::test]
+async fn test_invalid_credentials() {
+    let mock = MockLogto::serve(&["user-1"]).await;
+    let client = mock.client("wrong-secret");
+
+    let result = client.delete_user("user-1").await;
+    assert!(
+        matches!(
+            result,
+            Err(logto::Error::UnexpectedStatus(StatusCode::UNAUTHORIZED))
+        ),
+        "{result:?}"
+    );
+    assert_eq!(mock.users(), vec!["user-1"]);
+}
+
+#[tokio::test]
+async fn test_server_error() {
+    let mock = MockLogto::serve(&["user-1"]).await;
+    let client = mock.client(APP_SECRET);
+    mock.fail(true);
+
+    let result = client.delete_user("user-1").await;
+    assert!(
+        matches!(
+            result,
+            Err(logto::Error::UnexpectedStatus(
+                StatusCode::INTERNAL_SERVER_ERROR
+            ))
+        ),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_user_id_escaped() {
+    let mock = MockLogto::serve(&["user-1", "a/b"]).await;
+    let client = mock.client(APP_SECRET);
+
+    client
+        .delete_user("../users/user-1")
+        .await
+        .expect("missing user should be ignored");
+    assert_eq!(mock.users(), vec!["user-1", "a/b"]);
+
+    client
+        .delete_user("a/b")
+        .await
+        .expect("failed to delete user");
+    assert_eq!(mock.users(), vec!["user-1"]);
+
+    let result = client.delete_user("..").await;
+    assert!(
+        matches!(result, Err(logto::Error::InvalidUserId(_))),
+        "{result:?}"
+    );
+    assert_eq!(mock.users(), vec!["user-1"]);
+}
+
+#[tokio::test]
+async fn test_timeout() {
+    let mock = MockLogto::serve(&["user-1"]).await;
+    mock.hang(true);
+    let client = LogtoClient::new(LogtoConfig {
+        timeout: Duration::from_millis(100),
+        ..mock.config(APP_SECRET)
+    });
+
+    let result = tokio::time::timeout(Duration::from_secs(5), client.delete_user("user-1"))
+        .await
+        .expect("the request is not timed out");
+    assert!(
+        matches!(result, Err(logto::Error::Request(_))),
+        "{result:?}"
+    );
+    assert_eq!(mock.users(), vec!["user-1"]);
+}
@@ -0,0 +1,44 @@
+#![cfg(all(test, feature = "test_database"))]
+
+mod common;
+
+use backend::{metrics, rpc::dbrunner::RunQueryRequest};
+use common::dbrunner::MockDbRunner;
+use poem::{EndpointExt, Route, get, http::StatusCode, test::TestClient};
+use sqlx::PgPool;
+
+#[sqlx::test]
+async fn test_metrics(pool: PgPool) {
+    let handle = metrics::install().expect("failed to install the recorder");
+
+    let dbrunner = MockDbRunner::new(pool.clone()).serve().await;
+    dbrunner
+        .run_query(RunQueryRequest {
+            schema: "CREATE TABLE t (id int);".into(),
+            query: "SELECT * FROM t;".into(),
+        })
+        .await
+        .expect("failed to run query");
+
+    let app = Route::new()
+        .at("/metrics", get(metrics::render))
+        .data(handle)
+        .data(pool);
+
+    let response = TestClient::new(app).get("/metrics").send().await;
+    assert_eq!(response.0.status(), StatusCode::OK);
+    let body = response
+        .0
+        .into_body()
+        .into_string()
+        .await
+        .expect("invalid body");
+
+    assert!(body.contains(r#"dbrunner_calls_total{method="run_query",status="ok"} 1"#));
+    assert!(
+        body.contains(r#"dbrunner_call_duration_seconds_bucket{method="run_query",le="0.005"}"#)
+    );
+    assert!(body.contains(r#"db_pool_connections{state="idle"}"#));
+    assert!(body.contains(r#"db_pool_connections{state="in_use"}"#));
+    assert!(body.contains("db_pool_max_connections"));
+}
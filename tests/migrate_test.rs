@@ -0,0 +1,83 @@
+#![cfg(all(test, feature = "test_database"))]
+
+use backend::migrate::{self, MigrationState};
+use sqlx::PgPool;
+
+fn states(statuses: &[migrate::MigrationStatus]) -> Vec<MigrationState> {
+    statuses.iter().map(|status| status.state).collect()
+}
+
+#[sqlx::test(migrations = false)]
+async fn test_up_and_status(pool: PgPool) {
+    let statuses = migrate::status(&pool).await.expect("failed to get status");
+    assert!(!statuses.is_empty());
+    assert!(
+        states(&statuses)
+            .iter()
+            .all(|state| *state == MigrationState::Pending)
+    );
+    assert_eq!(statuses[0].description, "base");
+
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(&pool)
+        .await
+        .expect("failed to check table");
+    assert!(!exists, "the status does not create the table");
+
+    migrate::up(&pool).await.expect("failed to migrate");
+    migrate::up(&pool)
+        .await
+        .expect("migrating again should do nothing");
+
+    let statuses = migrate::status(&pool).await.expect("failed to get status");
+    assert!(
+        states(&statuses)
+            .iter()
+            .all(|state| *state == MigrationState::Applied)
+    );
+
+    sqlx::query("SELECT COUNT(*) FROM dp_api_tokens")
+        .fetch_one(&pool)
+        .await
+        .expect("the tables should be created");
+}
+
+#[sqlx::test(migrations = false)]
+async fn test_checksum_mismatch(pool: PgPool) {
+    migrate::up(&pool).await.expect("failed to migrate");
+
+    let first = migrate::status(&pool).await.expect("failed to get status")[0].version;
+    sqlx::query("UPDATE _sqlx_migrations SET checksum = '\\x00' WHERE version = $1")
+        .bind(first)
+        .execute(&pool)
+        .await
+        .expect("failed to change checksum");
+
+    let statuses = migrate::status(&pool).await.expect("failed to get status");
+    assert_eq!(statuses[0].state, MigrationState::ChecksumMismatch);
+    assert_eq!(statuses[1].state, MigrationState::Applied);
+}
+
+#[sqlx::test(migrations = false)]
+async fn test_unknown_migration(pool: PgPool) {
+    migrate::up(&pool).await.expect("failed to migrate");
+
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+        VALUES (99990101000000, 'from the future', TRUE, '\\x00', 0)",
+    )
+    .execute(&pool)
+    .await
+    .expect("failed to record migration");
+
+    let statuses = migrate::status(&pool).await.expect("failed to get status");
+    let last = statuses.last().expect("no migrations");
+    assert_eq!(last.version, 99990101000000);
+    assert_eq!(last.description, "from the future");
+    assert_eq!(last.state, MigrationState::Unknown);
+    assert!(
+        states(&statuses[..statuses.len() - 1])
+            .iter()
+            .all(|state| *state == MigrationState::Applied)
+    );
+}